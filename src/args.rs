@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, cell::{Cell, RefCell}, path::{Path, PathBuf}};
 use proc_macro::{TokenStream, TokenTree, Span, Group, Delimiter, Ident, Punct, Spacing};
 use string_tools::{get_all_before, get_all_after_strict};
 use crate::*;
@@ -7,10 +7,27 @@ use crate::*;
 pub(crate) struct Args {
     pub(crate) path: String,
     pub(crate) path_span: Span,
+    /// Name of the template section to use, when the file contains several templates
+    pub(crate) section: Option<String>,
+    /// Template markup given in the macro call, in which case `path` is the Rust file containing the call
+    pub(crate) inline: Option<String>,
+    /// Number of lines before the first line of the template in the file at `path`
+    #[cfg(feature = "i18n")]
+    pub(crate) line_offset: usize,
     #[cfg(feature = "i18n")]
     pub(crate) catalog: Catalog,
     auto_default: bool,
     vals: HashMap<String, TokenTree>,
+    /// Template-local variables in scope, declared by `<with>` elements
+    pub(crate) locals: RefCell<Vec<String>>,
+    /// Number of recursive elements being generated
+    pub(crate) recursion_depth: Cell<usize>,
+    /// Translation contexts set by the `i18n-context` attributes of the elements being generated
+    pub(crate) i18n_contexts: RefCell<Vec<String>>,
+    /// Number of elements opting out of translation among the elements being generated
+    pub(crate) untranslated_depth: Cell<usize>,
+    /// Reports of texts left untranslated in locales where it is denied, emitted once the code is generated
+    pub(crate) missing_translations: RefCell<Vec<String>>,
     pub(crate) config: Config,
 }
 
@@ -29,6 +46,24 @@ impl std::fmt::Display for ValOutput {
 }
 
 impl Args {
+    /// Returns the paths of the files the generated code depends on: the template file, config files and catalogs
+    pub(crate) fn dependencies(&self) -> Vec<PathBuf> {
+        let mut dependencies = Vec::new();
+        if self.inline.is_none() {
+            dependencies.push(crate_path(&self.path));
+        }
+        dependencies.extend(config_paths());
+        #[cfg(feature = "i18n")]
+        dependencies.extend(self.catalog.files.values().cloned());
+        dependencies.sort();
+        dependencies
+    }
+
+    /// Checks whether a value can be obtained for the given identifier
+    pub(crate) fn has_val(&self, id: &str) -> bool {
+        self.auto_default || self.vals.contains_key(id) || self.locals.borrow().iter().any(|local| local == id)
+    }
+
     pub(crate) fn get_val(&self, id: &str, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> ValOutput {
         // Handle helpers
         let mut parts = id.split(' ').filter(|p| !p.is_empty()).collect::<Vec<_>>();
@@ -54,7 +89,9 @@ impl Args {
         if id.starts_with("iter_") || id.ends_with("_iter") {
             iters.push(id.to_string());
         }
+        let is_local = self.locals.borrow().iter().any(|local| local == id);
         let mut val: TokenTree = match self.vals.get(id).map(|v| v.to_owned()) {
+            _ if is_local => TokenTree::Ident(Ident::new(id, Span::call_site())),
             Some(val) => val,
             None if self.auto_default => {
                 let val = TokenTree::Ident(Ident::new(id, Span::call_site()));
@@ -62,7 +99,7 @@ impl Args {
                     Some(field) => {
                         let mut token_stream = TokenStream::new();
                         token_stream.extend(vec![val, TokenTree::Punct(Punct::new('.', Spacing::Alone)), TokenTree::Ident(Ident::new(field, args.path_span))]);
-                        return ValOutput::TokenTree(TokenTree::Group(Group::new(Delimiter::Brace, token_stream)))
+                        return ValOutput::TokenTree(TokenTree::Group(Group::new(Delimiter::Parenthesis, token_stream)))
                     }
                     None => val
                 }
@@ -72,27 +109,102 @@ impl Args {
         if let Some(field) = field {
             let mut token_stream = TokenStream::new();
             token_stream.extend(vec![val, TokenTree::Punct(Punct::new('.', Spacing::Alone)), TokenTree::Ident(Ident::new(field, args.path_span))]);
-            val = TokenTree::Group(Group::new(Delimiter::Brace, token_stream));
+            val = TokenTree::Group(Group::new(Delimiter::Parenthesis, token_stream));
         }
         ValOutput::TokenTree(val)
     }
 }
 
+/// Returns the value of a Rust string literal, raw or not
+fn parse_str_literal(literal: &str) -> Option<String> {
+    // Raw string literals
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let raw = raw.get(hashes..raw.len().checked_sub(hashes)?)?;
+        return raw.strip_prefix('"')?.strip_suffix('"').map(|s| s.to_string());
+    }
+
+    // Regular string literals
+    let content = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            '0' => value.push('\0'),
+            '\\' => value.push('\\'),
+            '\'' => value.push('\''),
+            '"' => value.push('"'),
+            'x' => {
+                let code = chars.next()?.to_digit(16)? * 16 + chars.next()?.to_digit(16)?;
+                value.push(char::from_u32(code)?);
+            }
+            'u' => {
+                chars.next().filter(|c| *c == '{')?;
+                let code = chars.by_ref().take_while(|c| *c != '}').collect::<String>();
+                value.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+            }
+            '\n' => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+/// Returns the path of a template relative to the crate root, given its path relative to the Rust file at `file`.
+/// The path of `file` is relative to the working directory of rustc, which is the workspace root for workspace members.
+fn path_relative_to_file(path: &str, file: &Path, working_directory: &Path, crate_root: &Path) -> String {
+    let path = working_directory.join(file).parent().unwrap_or(Path::new("")).join(path);
+    path.strip_prefix(crate_root).unwrap_or(&path).to_string_lossy().into_owned()
+}
+
 pub(crate) fn parse_args(args: TokenStream) -> Args {
     let config = config::read_config();
     
     let mut tokens = args.into_iter().peekable();
     let _ = tokens.peek();
 
-    // Extract the first parameter: path
-    let (path, path_span) = match tokens.next() {
+    // Extract the first parameter: path or inline template
+    let mut inline = None;
+    #[cfg(feature = "i18n")]
+    let mut line_offset = 0;
+    let (mut path, section, path_span) = match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "inline" => {
+            match tokens.next() {
+                Some(TokenTree::Punct(punct)) if punct.as_char() == '=' => (),
+                Some(t) => abort!(t.span(), "Expected an equal sign after inline"),
+                None => abort!(ident.span(), "Expected the template markup after inline"),
+            }
+            let lit = match tokens.next() {
+                Some(TokenTree::Literal(lit)) => lit,
+                Some(t) => abort!(t.span(), "Expected a string literal containing the template markup"),
+                None => abort!(ident.span(), "Expected the template markup after inline"),
+            };
+            let Some(template) = parse_str_literal(&lit.to_string()) else {
+                abort!(lit.span(), "Expected a string literal containing the template markup");
+            };
+            inline = Some(template);
+            #[cfg(feature = "i18n")]
+            { line_offset = lit.span().line().saturating_sub(1); }
+            (lit.span().file(), None, lit.span())
+        },
         Some(TokenTree::Literal(lit)) => {
             let path = lit.to_string();
             if !path.starts_with('"') || !path.ends_with('"') {
                 abort!(lit.span(), "Expected a string literal being the path to the template file");
             }
             let path = path[1..path.len() - 1].to_string();
-            (format!("{}{}", config.template_directory, path), lit.span())
+            let (path, section) = match path.split_once('#') {
+                Some((path, section)) => (path.to_string(), Some(section.to_string())),
+                None => (path, None),
+            };
+            (path, section, lit.span())
         },
         Some(t) => abort!(t.span(), "First parameter should be a string literal of the path to the template file"),
         None => abort_call_site!("Please specify the path to the template file as the first parameter"),
@@ -101,6 +213,7 @@ pub(crate) fn parse_args(args: TokenStream) -> Args {
     let mut vals = HashMap::new();
     let mut comma_passed = false;
     let mut auto_default = config.auto_default;
+    let mut relative_to = config.templates_relative_to;
     loop {
         // Check comma
         if !comma_passed {
@@ -168,11 +281,57 @@ pub(crate) fn parse_args(args: TokenStream) -> Args {
             }
         }
 
+        // The template_rel option isn't a template variable
+        if id == "template_rel" {
+            let base = match &value {
+                TokenTree::Literal(lit) => PathBase::parse(lit.to_string().trim_matches('"')),
+                _ => None,
+            };
+            match base {
+                Some(base) => relative_to = base,
+                None => abort!(value.span(), "Expected \"crate\" or \"file\" as the value of template_rel"),
+            }
+            continue;
+        }
+
         vals.insert(id, value);
     }
 
+    // Resolve the path of template files
+    if inline.is_none() {
+        path = match relative_to {
+            PathBase::Crate => format!("{}{}", config.template_directory, path),
+            PathBase::File => {
+                let Some(file) = path_span.local_file() else {
+                    abort!(path_span, "Cannot resolve the template path relative to the invoking file because its location is unknown");
+                };
+                let working_directory = std::env::current_dir().unwrap_or_default();
+                path_relative_to_file(&path, &file, &working_directory, &crate_path(""))
+            },
+        };
+    }
+
     #[cfg(feature = "i18n")]
-    let catalog = Catalog::new(&config.locale_directory);
+    let catalog = Catalog::new(&crate_path(&config.locale_directory), config.include_fuzzy);
+
+    Args { path, path_span, section, inline, #[cfg(feature = "i18n")] line_offset, vals, locals: RefCell::new(Vec::new()), recursion_depth: Cell::new(0), i18n_contexts: RefCell::new(Vec::new()), untranslated_depth: Cell::new(0), missing_translations: RefCell::new(Vec::new()), auto_default, #[cfg(feature = "i18n")] catalog, config }
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_str_literal() {
+    assert_eq!(parse_str_literal(r#""Hello \"World\"\n""#).as_deref(), Some("Hello \"World\"\n"));
+    assert_eq!(parse_str_literal(r#""\u{e9}t\x65 \
+        continued""#).as_deref(), Some("éte continued"));
+    assert_eq!(parse_str_literal(r###"r#"<p class="a">{{name}}</p>"#"###).as_deref(), Some("<p class=\"a\">{{name}}</p>"));
+    assert_eq!(parse_str_literal("r\"raw\"").as_deref(), Some("raw"));
+    assert_eq!(parse_str_literal("42"), None);
+}
 
-    Args { path, path_span, vals, auto_default, #[cfg(feature = "i18n")] catalog, config }
+#[test]
+fn test_path_relative_to_file() {
+    assert_eq!(path_relative_to_file("badge.html", Path::new("src/feat/mod.rs"), Path::new("/ws/app"), Path::new("/ws/app")), "src/feat/badge.html");
+    assert_eq!(path_relative_to_file("badge.html", Path::new("app/src/feat/mod.rs"), Path::new("/ws"), Path::new("/ws/app")), "src/feat/badge.html");
+    assert_eq!(path_relative_to_file("../badge.html", Path::new("/ws/app/src/lib.rs"), Path::new("/ws"), Path::new("/ws/app")), "src/../badge.html");
+    assert_eq!(path_relative_to_file("badge.html", Path::new("/elsewhere/lib.rs"), Path::new("/ws"), Path::new("/ws/app")), "/elsewhere/badge.html");
 }
@@ -14,36 +14,92 @@ pub(crate) fn text_part_to_code(text_part: &TextPart, opts: &mut Vec<String>, it
         }
         TextPart::Expression(id) => {
             let mut value = args.get_val(id, opts, iters, args).to_string();
-            if id.starts_with("opt_") || id.ends_with("_opt") || id.starts_with("iter_") || id.ends_with("_iter") {
-                value = format!("macro_produced_{id}");
+            let (base_id, field) = (get_all_before(id, "."), get_all_after_strict(id, "."));
+            if !id.contains(' ') && (base_id.starts_with("opt_") || base_id.ends_with("_opt") || base_id.starts_with("iter_") || base_id.ends_with("_iter")) {
+                value = match field {
+                    Some(field) => format!("(macro_produced_{base_id}.{field})"),
+                    None => format!("macro_produced_{base_id}"),
+                };
             };
             format!("{{{value}}}")
         },
     }
 }
 
+/// Checks whether a `ref` attribute value is the name of a node ref rather than a template expression
+pub(crate) fn is_ref_name(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Checks whether a name can be used as the identifier of a template-local variable
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') && !name.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Attributes used by yew-template, which are not rendered
+const MARKER_ATTRIBUTES: &[&str] = &["opt", "iter", "present-if", "interpolate", "safe-urls", "unsafe-urls", "recursive", "i18n", "i18n-context", "i18n-note", "notranslate"];
+
+/// Checks whether an attribute is used by yew-template, including `translate="no"` which opts out of translation
+fn is_marker_attribute(name: &str, value: &str) -> bool {
+    MARKER_ATTRIBUTES.contains(&name) || (name == "translate" && value == "no")
+}
+
 /// Turns an HTML attribute to Rust code for Yew
 pub(crate) fn attr_to_code((name, value): (String, String), opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> Option<String> {
     // Remove attributes used by yew-template
-    if name == "opt" || name == "iter" || name == "present-if" {
+    if is_marker_attribute(&name, &value) {
         return None
     }
 
+    // Handle event handler sugar
+    if name.starts_with("on:") {
+        return Some(event_attr_to_code(&name, &value, opts, iters, args))
+    }
+
+    // Bind node refs declared by name
+    if name == "ref" && is_ref_name(&value) {
+        if !args.has_val(&value) {
+            abort!(args.path_span, "Template {} declares the node ref {:?} but it was not provided", args.path, value);
+        }
+        let node_ref = args.get_val(&value, opts, iters, args);
+        return Some(format!("ref={{({node_ref}).clone()}}"))
+    }
+
+    // Get localized values of attributes shown to users
+    #[cfg(feature = "i18n")]
+    if args.config.translatable_attributes.contains(&name) && args.untranslated_depth.get() == 0 && is_translatable(&value, args) {
+        let translations = args.catalog.translate_attribute(&value, &name, args);
+        if translations.len() > 1 {
+            let locale_code = LOCALE_VARIABLE;
+            let arms = translations.into_iter().enumerate().rev().map(|(i, (locale, translation))| {
+                let arm = match i == 0 {
+                    true => String::from("_"),
+                    false => escaped_str_code(&locale),
+                };
+                let code = attr_value_to_code(translation, opts, iters, args).unwrap_or_else(|| String::from("\"\""));
+                format!("{arm} => ({code}).to_string(),")
+            }).collect::<Vec<_>>().join(" ");
+            return Some(format!("{name}={{match {locale_code} {{ {arms} }}}}"))
+        }
+    }
+
     // Split text into text parts
     let text_parts = TextPart::parse(&value, args);
+    if let [TextPart::Literal(text)] = text_parts.as_slice() {
+        if text == "true" || text == "false" {
+            return Some(format!("{name}={{{text}}}"))
+        }
+    }
 
     // Generate code
+    attr_value_to_code(text_parts, opts, iters, args).map(|code| format!("{name}={code}"))
+}
+
+/// Turns the text parts of an attribute value to a Rust expression
+fn attr_value_to_code(text_parts: Vec<TextPart>, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> Option<String> {
     match text_parts.len() {
         0 => None,
-        1 => {
-            if let TextPart::Literal(text) = &text_parts[0] {
-                if text == "true" || text == "false" {
-                    return Some(format!("{name}={{{text}}}"))
-                }
-            }
-            let text_part_code = text_parts[0].to_code(opts, iters, args);
-            Some(format!("{name}={text_part_code}"))
-        }
+        1 => Some(text_parts[0].to_code(opts, iters, args)),
         _ => {
             let mut format_literal = String::new();
             let mut format_args = Vec::new();
@@ -66,13 +122,35 @@ pub(crate) fn attr_to_code((name, value): (String, String), opts: &mut Vec<Strin
             }
             let format_literal = escaped_str_code(&format_literal);
             let format_args = format_args.join(", ");
-            Some(format!("{name}={{format!({format_literal}, {format_args})}}"))
+            Some(format!("{{format!({format_literal}, {format_args})}}"))
         }
     }
 }
 
+/// Attributes containing URLs, which are checked at runtime in safe URL mode
+const URL_ATTRIBUTES: [&str; 5] = ["href", "src", "action", "formaction", "xlink:href"];
+
+/// Turns a URL-bearing HTML attribute to Rust code for Yew, replacing URLs with disallowed schemes at runtime
+pub(crate) fn url_attr_to_code(attr: (String, String), opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> Option<String> {
+    let has_variables = TextPart::parse(&attr.1, args).iter().any(|p| matches!(p, TextPart::Expression(_)));
+    let code = attr_to_code(attr, opts, iters, args)?;
+    if !has_variables {
+        return Some(code);
+    }
+    let (name, value_code) = code.split_once('=')?;
+    let schemes = args.config.safe_url_schemes.iter().map(|s| escaped_str_code(&s.to_ascii_lowercase())).collect::<Vec<_>>().join(", ");
+    Some(format!("{name}={{{{\
+        let url = ({value_code}).to_string(); \
+        let compact_url = url.chars().filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control()).collect::<String>(); \
+        match compact_url.find([':', '/', '?', '#']) {{ \
+            Some(idx) if compact_url[idx..].starts_with(':') && ![{schemes}].contains(&compact_url[..idx].to_ascii_lowercase().as_str()) => String::from(\"about:invalid\"), \
+            _ => url, \
+        }}\
+    }}}}"))
+}
+
 /// Turns an HTML element and its children to Rust code for Yew
-pub(crate) fn element_to_code(el: Element, depth: usize, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> String {
+pub(crate) fn element_to_code(mut el: Element, depth: usize, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> String {
     let tabs = "    ".repeat(depth);
 
     // Make sure the element is valid
@@ -87,11 +165,121 @@ pub(crate) fn element_to_code(el: Element, depth: usize, opts: &mut Vec<String>,
     let opt = el.open_attrs.iter().any(|(n,_)| n=="opt");
     let iter = el.open_attrs.iter().any(|(n,_)| n=="iter");
     let present_if = el.open_attrs.iter().find(|(n,_)| n=="present-if").map(|(_,v)| v.to_owned());
+    let safe_urls = (args.config.safe_urls || el.open_attrs.iter().any(|(n,_)| n=="safe-urls")) && !el.open_attrs.iter().any(|(n,_)| n=="unsafe-urls");
+    if el.open_attrs.iter().any(|(n,_)| n=="safe-urls" || n=="unsafe-urls") {
+        el.propagate_marker(if safe_urls {"safe-urls"} else {"unsafe-urls"}, &["safe-urls", "unsafe-urls"]);
+    }
+
+    // Declare the parameter of recursive elements
+    let recursive = el.open_attrs.iter().find(|(n,_)| n=="recursive").map(|(_,v)| v.to_owned());
+    let mut recursion_start = None;
+    if let Some(parameter) = &recursive {
+        if !is_identifier(parameter) {
+            abort!(args.path_span, "Invalid recursion parameter name {:?} in template {}", parameter, args.path);
+        }
+        recursion_start = Some(args.get_val(parameter, &mut Vec::new(), &mut Vec::new(), args));
+        args.locals.borrow_mut().push(parameter.to_owned());
+        args.recursion_depth.set(args.recursion_depth.get() + 1);
+    }
+
+    // Set the translation context of the texts inside the element, or disable their translation
+    let i18n_context = el.open_attrs.iter().find(|(n,_)| n=="i18n-context").map(|(_,v)| v.to_owned());
+    if let Some(i18n_context) = &i18n_context {
+        args.i18n_contexts.borrow_mut().push(i18n_context.to_owned());
+    }
+    let untranslated = el.is_untranslated();
+    if untranslated {
+        args.untranslated_depth.set(args.untranslated_depth.get() + 1);
+    }
 
     // Scan and generate children
     let mut inner_opts = Vec::new();
     let mut inner_iters = Vec::new();
-    let mut f_open_attrs = el.open_attrs.into_iter().filter_map(|a| attr_to_code(a, &mut inner_opts, &mut inner_iters, args)).collect::<Vec<_>>().join(" ");
+
+    // Get the name of dynamic tags
+    let mut dynamic_tag = None;
+    if el.name == "dynamic" {
+        let Some(idx) = el.open_attrs.iter().position(|(n,_)| n=="tag") else {
+            abort!(args.path_span, "Dynamic elements require a tag attribute in template {}", args.path);
+        };
+        let tag = el.open_attrs.remove(idx);
+        dynamic_tag = attr_to_code(tag, &mut inner_opts, &mut inner_iters, args).and_then(|c| c.split_once('=').map(|(_, c)| c.to_owned()));
+    }
+
+    // Get the value to recurse with
+    let mut recursion_value = None;
+    if el.name == "recurse" {
+        if args.recursion_depth.get() == 0 {
+            abort!(args.path_span, "Recurse elements must be inside a recursive element in template {}", args.path);
+        }
+        let Some(idx) = el.open_attrs.iter().position(|(n,_)| n=="with") else {
+            abort!(args.path_span, "Recurse elements require a with attribute in template {}", args.path);
+        };
+        let (_, value) = el.open_attrs.remove(idx);
+        let [text_part @ TextPart::Expression(_)] = &TextPart::parse(&value, args)[..] else {
+            abort!(args.path_span, "The with attribute of recurse elements must be a variable in template {}", args.path);
+        };
+        recursion_value = Some(text_part.to_code(&mut inner_opts, &mut inner_iters, args));
+    }
+
+    // Bind template-local variables
+    let mut local_bindings = String::new();
+    let mut local_count = 0;
+    if el.name == "with" {
+        let (special_attrs, local_attrs) = el.open_attrs.into_iter().partition(|(n, v)| is_marker_attribute(n, v));
+        el.open_attrs = special_attrs;
+        for (local, value) in local_attrs {
+            if !is_identifier(&local) {
+                abort!(args.path_span, "Invalid local variable name {:?} in template {}", local, args.path);
+            }
+            let Some((_, code)) = attr_to_code((local.clone(), value), &mut inner_opts, &mut inner_iters, args).and_then(|c| c.split_once('=').map(|(n, c)| (n.to_owned(), c.to_owned()))) else {
+                abort!(args.path_span, "Missing value for local variable {:?} in template {}", local, args.path);
+            };
+            local_bindings.push_str(&format!("let {local} = {code}; "));
+            args.locals.borrow_mut().push(local);
+            local_count += 1;
+        }
+    }
+
+    // Get the forms of texts depending on a count
+    let plural_forms = el.open_attrs.iter().find(|(n, _)| n == "plural").map(|(_, count)| match el.get_plural_forms() {
+        Some(forms) => (count.to_owned(), forms),
+        None => abort!(args.path_span, "Elements with a plural attribute must only contain text and have a plural-other attribute or sibling in template {}", args.path),
+    });
+    if plural_forms.is_none() && el.open_attrs.iter().any(|(n, _)| n == "plural-other") {
+        abort!(args.path_span, "plural-other attributes require a plural attribute in template {}", args.path);
+    }
+
+    let mut open_attrs = el.open_attrs;
+    let mut children = el.children;
+    if plural_forms.is_some() {
+        open_attrs.retain(|(n, _)| n != "plural" && n != "plural-other");
+        children.clear();
+    }
+    #[cfg(feature = "i18n")]
+    let markup = args.untranslated_depth.get() == 0 && open_attrs.iter().any(|(n, _)| n == "i18n");
+
+    // Get the raw HTML to use as content
+    let raw_html = open_attrs.iter().position(|(n, _)| n == "html" || n == "html-unchecked").map(|idx| open_attrs.remove(idx));
+    if raw_html.is_some() && !children.is_empty() {
+        abort!(args.path_span, "Elements with an html attribute cannot have children");
+    }
+
+    // Textarea contents are set through their value
+    let mut textarea_value = None;
+    if el.name == "textarea" && !open_attrs.iter().any(|(n, _)| n == "value" || n == "bind:value") {
+        if let Some(HtmlPartWithLine { part: HtmlPart::RawText { text, interpolate }, .. }) = children.pop() {
+            match interpolate {
+                true => open_attrs.push((String::from("value"), text)),
+                false => textarea_value = Some(format!("value={{{}}}", escaped_str_code(&text))),
+            }
+        }
+    }
+    let bindings = bind_attrs_to_code(&mut open_attrs, &mut inner_opts, &mut inner_iters, args);
+    let mut f_open_attrs = open_attrs.into_iter().filter_map(|a| match safe_urls && URL_ATTRIBUTES.contains(&a.0.as_str()) {
+        true => url_attr_to_code(a, &mut inner_opts, &mut inner_iters, args),
+        false => attr_to_code(a, &mut inner_opts, &mut inner_iters, args),
+    }).chain(bindings).chain(textarea_value).collect::<Vec<_>>().join(" ");
     if !f_open_attrs.is_empty() {
         f_open_attrs.insert(0, ' ');
     }
@@ -100,21 +288,65 @@ pub(crate) fn element_to_code(el: Element, depth: usize, opts: &mut Vec<String>,
         f_close_attrs.insert(0, ' ');
     }
     let name = el.name;
-    let mut content = el.children.into_iter().map(|p| p.part.into_code(depth + 1, &mut inner_opts, &mut inner_iters, args)).collect::<Vec<_>>().join("");
+    let mut content = String::new();
+    #[cfg(feature = "i18n")]
+    if markup {
+        content = markup_to_code(std::mem::take(&mut children), depth + 1, &mut inner_opts, &mut inner_iters, args);
+    }
+    content.push_str(&children.into_iter().map(|p| p.part.into_code(depth + 1, &mut inner_opts, &mut inner_iters, args)).collect::<Vec<_>>().join(""));
+    if let Some((count, (singular, plural))) = plural_forms {
+        content.push_str(&plural_to_code(&count, singular, plural, depth + 1, &mut inner_opts, &mut inner_iters, args));
+    }
+    if let Some((raw_html_attr, raw_html)) = raw_html {
+        #[cfg(feature = "raw-html")]
+        content.push_str(&raw_html_to_code(&raw_html, raw_html_attr == "html", depth + 1, &mut inner_opts, &mut inner_iters, args));
+        #[cfg(not(feature = "raw-html"))]
+        abort!(args.path_span, "The {} attribute with value {:?} requires the \"raw-html\" feature", raw_html_attr, raw_html);
+    }
+    let locals_len = args.locals.borrow().len();
+    args.locals.borrow_mut().truncate(locals_len - local_count);
+    if i18n_context.is_some() {
+        args.i18n_contexts.borrow_mut().pop();
+    }
+    if untranslated {
+        args.untranslated_depth.set(args.untranslated_depth.get() - 1);
+    }
     inner_opts.sort();
     inner_opts.dedup();
     inner_iters.sort();
     inner_iters.dedup();
 
-    // Handle special virtual elements
-    content = match name == "virtual" {
-        true => {
+    // Handle special virtual and with elements
+    content = match name.as_str() {
+        "virtual" => {
             if !f_open_attrs.is_empty() || !f_close_attrs.is_empty() {
                 abort!(args.path_span, "Virtual elements cannot have attributes (found {:?} and {:?})", f_open_attrs, f_close_attrs);
             }
             content.replace("\n    ", "\n")
         },
-        false => match el.self_closing {
+        "recurse" => {
+            if !f_open_attrs.is_empty() || !f_close_attrs.is_empty() || !content.is_empty() {
+                abort!(args.path_span, "Recurse elements cannot have children nor attributes other than with (found {:?} and {:?})", f_open_attrs, f_close_attrs);
+            }
+            format!("\n{tabs}{{macro_produced_recurse({})}}", recursion_value.unwrap_or_default())
+        },
+        "dynamic" => {
+            if !f_close_attrs.is_empty() {
+                abort!(args.path_span, "Dynamic elements cannot have closing attributes (found {:?})", f_close_attrs);
+            }
+            let tag = dynamic_tag.unwrap_or_default();
+            match el.self_closing {
+                true => format!("\n{tabs}<@{tag}{f_open_attrs}/>"),
+                false => format!("\n{tabs}<@{tag}{f_open_attrs}>{content}\n{tabs}</@>"),
+            }
+        },
+        "with" => {
+            if !f_close_attrs.is_empty() {
+                abort!(args.path_span, "With elements cannot have closing attributes (found {:?})", f_close_attrs);
+            }
+            format!("\n{tabs}{{{{ {local_bindings}yew::html! {{ <> {content}\n{tabs}</> }} }}}}")
+        },
+        _ => match el.self_closing {
             true if &name == "br" => format!("<{name} {f_open_attrs}/>"),
             true => format!("\n{tabs}<{name}{f_open_attrs}/>"),
             false => format!("\n{tabs}<{name}{f_open_attrs}>{content}\n{tabs}</{name}{f_close_attrs}>"),
@@ -178,6 +410,21 @@ pub(crate) fn element_to_code(el: Element, depth: usize, opts: &mut Vec<String>,
         );
     }
 
+    // Handle recursive elements
+    if let (Some(parameter), Some(recursion_start)) = (recursive, recursion_start) {
+        args.locals.borrow_mut().pop();
+        args.recursion_depth.set(args.recursion_depth.get() - 1);
+        content = content.replace('\n', "\n    ");
+        content = format!("\n\
+            {tabs}{{{{\n\
+            {tabs}fn macro_produced_fix<T, F: Fn(&dyn Fn(T) -> yew::Html, T) -> yew::Html>(f: &F, t: T) -> yew::Html {{ f(&|t| macro_produced_fix(f, t), t) }}\n\
+            {tabs}fn macro_produced_recursive<T, F: Fn(&dyn Fn(T) -> yew::Html, T) -> yew::Html>(t: T, f: F) -> yew::Html {{ macro_produced_fix(&f, t) }}\n\
+            {tabs}macro_produced_recursive({recursion_start}, |macro_produced_recurse, {parameter}| yew::html! {{ <> {content}\n\
+            {tabs}</> }})\n\
+            {tabs}}}}}"
+        );
+    }
+
     content
 }
 
@@ -185,7 +432,10 @@ pub(crate) fn element_to_code(el: Element, depth: usize, opts: &mut Vec<String>,
 pub(crate) fn text_to_code(text: String, depth: usize, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> String {
     let tabs = "    ".repeat(depth);
 
-    // If it's only a single variable then no need to translate
+    // If it's only whitespace or a single variable then no need to translate
+    if text.trim().is_empty() {
+        return format!("\n{tabs}{{{}}}", escaped_str_code(&text));
+    }
     let text_parts = TextPart::parse(&text, args);
     if matches!(text_parts.as_slice(), &[TextPart::Expression(_)]) {
         return text_parts[0].to_code(opts, iters, args);
@@ -193,7 +443,10 @@ pub(crate) fn text_to_code(text: String, depth: usize, opts: &mut Vec<String>, i
 
     // Get localized texts
     #[cfg(feature = "i18n")]
-    let translations = args.catalog.translate_text(&text, args);
+    let translations = match args.untranslated_depth.get() {
+        0 => args.catalog.translate_text(&text, args),
+        _ => vec![(String::new(), text_parts)],
+    };
     #[cfg(not(feature = "i18n"))]
     let translations = vec![(String::new(), text_parts)];
 
@@ -210,7 +463,7 @@ pub(crate) fn text_to_code(text: String, depth: usize, opts: &mut Vec<String>, i
             break;
         }
     }
-    let locale_code = &args.config.locale_code;
+    let locale_code = LOCALE_VARIABLE;
     if all_are_single_literal {
         let mut result = String::new();
         result.push_str(&format!("\n{tabs}{{match {locale_code} {{\n"));
@@ -246,9 +499,179 @@ pub(crate) fn text_to_code(text: String, depth: usize, opts: &mut Vec<String>, i
     result
 }
 
+/// Turns the text of an element with a `plural` attribute to Rust code for Yew, selecting the form matching the count in each locale
+pub(crate) fn plural_to_code(count: &str, singular: String, plural: String, depth: usize, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> String {
+    let tabs = "    ".repeat(depth);
+    let [count @ TextPart::Expression(_)] = &TextPart::parse(count, args)[..] else {
+        abort!(args.path_span, "The plural attribute must be a variable in template {}", args.path);
+    };
+    let count = count.to_code(opts, iters, args);
+
+    // Get localized forms
+    #[cfg(feature = "i18n")]
+    let translations = match args.untranslated_depth.get() {
+        0 => args.catalog.translate_plural(&singular, &plural, args),
+        _ => vec![(String::new(), None, vec![TextPart::parse(&singular, args), TextPart::parse(&plural, args)])],
+    };
+    #[cfg(not(feature = "i18n"))]
+    let translations = [(String::new(), None::<String>, [TextPart::parse(&singular, args), TextPart::parse(&plural, args)])];
+
+    let locale_code = LOCALE_VARIABLE;
+    let mut result = String::new();
+    result.push_str(&format!("\n{tabs}{{{{ let macro_produced_count = ({count}) as i64; match {locale_code} {{\n"));
+    for (i, (locale, formula, forms)) in translations.iter().enumerate().rev() {
+        let arm = match i == 0 {
+            true => String::from("_"),
+            false => escaped_str_code(locale),
+        };
+        let formula = formula.to_owned().unwrap_or_else(|| String::from("(macro_produced_count != 1) as i64"));
+        result.push_str(&format!("{tabs}    {arm} => match {formula} {{\n"));
+        for (j, form) in forms.iter().enumerate() {
+            let form_arm = match j + 1 == forms.len() {
+                true => String::from("_"),
+                false => j.to_string(),
+            };
+            let code = form.to_code(opts, iters, args);
+            result.push_str(&format!("{tabs}        {form_arm} => yew::html! {{ <> {code} </> }},\n"));
+        }
+        result.push_str(&format!("{tabs}    }},\n"));
+    }
+    result.push_str(&format!("{tabs}}}}}}}"));
+
+    result
+}
+
+/// Turns the content of an element with an `i18n` attribute to Rust code for Yew, translating it as a whole
+#[cfg(feature = "i18n")]
+pub(crate) fn markup_to_code(children: Vec<HtmlPartWithLine>, depth: usize, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> String {
+    let tabs = "    ".repeat(depth);
+    let container = Element { name: String::new(), self_closing: false, open_attrs: Vec::new(), close_attrs: Vec::new(), children };
+    let mut placeholders = Vec::new();
+    let msgid = container.markup_msgid(&mut placeholders, args);
+
+    let translated_msgids = args.catalog.translate_markup(&msgid, args);
+
+    // Translate the attributes of placeholders once for all locales, binding them to template-local variables
+    let mut placeholders = placeholders.into_iter().cloned().collect::<Vec<_>>();
+    let mut attribute_bindings = String::new();
+    let locals_len = args.locals.borrow().len();
+    if !translated_msgids.is_empty() && args.untranslated_depth.get() == 0 {
+        for (number, placeholder) in placeholders.iter_mut().enumerate() {
+            for (name, value) in &mut placeholder.open_attrs {
+                if !args.config.translatable_attributes.contains(name) || !is_translatable(value, args) {
+                    continue;
+                }
+                let Some((_, code)) = attr_to_code((name.clone(), value.clone()), opts, iters, args).and_then(|c| c.split_once('=').map(|(n, c)| (n.to_owned(), c.to_owned()))) else {
+                    continue;
+                };
+                let local = format!("macro_produced_attribute_{number}_{}", name.replace('-', "_"));
+                attribute_bindings.push_str(&format!("let {local} = {code}; "));
+                *value = format!("{}{local}{}", args.config.variable_bounds.0, args.config.variable_bounds.1);
+                args.locals.borrow_mut().push(local);
+            }
+        }
+    }
+    let placeholders = placeholders.iter().collect::<Vec<_>>();
+
+    // Rebuild the content in each locale
+    let mut translations = Vec::new();
+    match markup_to_children(&msgid, &placeholders, 0) {
+        Some(children) => translations.push((String::new(), children)),
+        None => abort!(args.path_span, "Text {:?} of an element with an i18n attribute contains malformed placeholders in template {}", msgid, args.path),
+    }
+    for (locale, translation) in translated_msgids {
+        match markup_to_children(&translation, &placeholders, 0) {
+            Some(children) => translations.push((locale, children)),
+            None => report_untranslated(format!("Translation {translation:?} of text {msgid:?} in language {locale} has malformed or unknown placeholders"), &locale, args),
+        }
+    }
+    let children_depth = match translations.len() {
+        1 => depth,
+        _ => depth + 2,
+    };
+    let mut translations = translations.into_iter().map(|(locale, children)| {
+        let code = children.into_iter().map(|p| p.part.into_code(children_depth, opts, iters, args)).collect::<Vec<_>>().join("");
+        (locale, code)
+    }).collect::<Vec<_>>();
+    args.locals.borrow_mut().truncate(locals_len);
+
+    // Translations are disabled
+    if translations.len() == 1 {
+        return translations.remove(0).1;
+    }
+
+    let locale_code = LOCALE_VARIABLE;
+    let mut result = String::new();
+    result.push_str(&format!("\n{tabs}{{{{ {attribute_bindings}match {locale_code} {{\n"));
+    for (i, (locale, code)) in translations.iter().enumerate().rev() {
+        let arm = match i == 0 {
+            true => String::from("_"),
+            false => escaped_str_code(locale),
+        };
+        result.push_str(&format!("{tabs}    {arm} => yew::html! {{ <> {code}\n{tabs}    </> }},\n"));
+    }
+    result.push_str(&format!("{tabs}}}}}}}"));
+
+    result
+}
+
+/// Turns the text of raw-text and preformatted elements to Rust code for Yew
+pub(crate) fn raw_text_to_code(text: String, interpolate: bool, depth: usize, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> String {
+    let tabs = "    ".repeat(depth);
+    match interpolate {
+        true => format!("\n{tabs}{}", TextPart::parse(&text, args).to_code(opts, iters, args)),
+        false => format!("\n{tabs}{{{}}}", escaped_str_code(&text)),
+    }
+}
+
+/// Variable holding the locale whose translations are displayed
+const LOCALE_VARIABLE: &str = "macro_produced_locale";
+
+/// Returns Rust code picking the locale to display among the available ones, based on the locale evaluated at runtime.
+/// Regional variants such as `fr-CA` or `fr_FR` fall back to their language, and then to the default locale.
+fn locale_selection_code(args: &Args) -> String {
+    #[cfg(feature = "i18n")]
+    let locales = args.catalog.locales().iter().map(|l| escaped_str_code(l)).collect::<Vec<_>>().join(", ");
+    #[cfg(not(feature = "i18n"))]
+    let locales = String::new();
+    let locale_code = &args.config.locale_code;
+    let default_locale = escaped_str_code(&args.config.default_locale);
+    format!("let {LOCALE_VARIABLE}: &str = {{ \
+        let mut macro_produced_requested_locale: &str = {locale_code}; \
+        let macro_produced_available_locales: &[&'static str] = &[{locales}]; \
+        let macro_produced_same_char = |(a, b): (u8, u8)| a.eq_ignore_ascii_case(&b) || (matches!(a, b'-' | b'_') && matches!(b, b'-' | b'_')); \
+        loop {{ \
+            if let Some(macro_produced_locale_match) = macro_produced_available_locales.iter().find(|l| l.len() == macro_produced_requested_locale.len() && l.bytes().zip(macro_produced_requested_locale.bytes()).all(macro_produced_same_char)) {{ \
+                break *macro_produced_locale_match; \
+            }} \
+            match macro_produced_requested_locale.rfind(['-', '_']) {{ \
+                Some(idx) => macro_produced_requested_locale = &macro_produced_requested_locale[..idx], \
+                None => break {default_locale}, \
+            }} \
+        }} \
+    }};")
+}
+
 pub(crate) fn generate_code(root: Element, args: Args) -> String {
     let yew_html = HtmlPart::Element(root).into_code(0, &mut Vec::new(), &mut Vec::new(), &args);
-    let yew_code = format!("yew::html! {{ {yew_html} }}");
+    let mut yew_code = format!("yew::html! {{ {yew_html} }}");
+
+    // Report all texts left untranslated in locales denying it in a single error
+    let missing_translations = args.missing_translations.take();
+    if !missing_translations.is_empty() {
+        abort_call_site!("Some texts are left untranslated:\n{}", missing_translations.join("\n"));
+    }
+
+    // Select the locale once for all translated texts
+    if yew_html.contains(LOCALE_VARIABLE) {
+        yew_code = format!("{{ {} {yew_code} }}", locale_selection_code(&args));
+    }
 
-    yew_code
+    // Make cargo rebuild the invoking crate when files read by the macro change
+    let dependencies = args.dependencies().into_iter().filter(|path| path.is_absolute()).collect::<Vec<_>>();
+    if dependencies.is_empty() {
+        return yew_code;
+    }
+    let includes = dependencies.iter().map(|path| format!("const _: &[u8] = include_bytes!({:?});", path.to_string_lossy())).collect::<Vec<_>>().join(" ");
+    format!("{{ {includes} {yew_code} }}")
 }
@@ -1,3 +1,4 @@
+use std::path::{Path, PathBuf};
 use crate::*;
 
 #[cfg_attr(feature = "config", derive(serde::Deserialize, serde::Serialize))]
@@ -16,6 +17,55 @@ impl AnyValues {
     }
 }
 
+/// How whitespace in template texts is handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
+#[cfg_attr(not(feature = "config"), allow(dead_code))]
+pub enum Whitespace {
+    /// Keep whitespace as it appears in templates
+    Preserve,
+    /// Collapse whitespace sequences into a single space, like HTML does
+    Collapse,
+    /// Remove whitespace at the beginning and end of texts
+    Trim,
+}
+
+/// What template paths are relative to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
+pub enum PathBase {
+    /// The template directory
+    Crate,
+    /// The directory of the Rust file containing the macro call
+    File,
+}
+
+impl PathBase {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "crate" => Some(PathBase::Crate),
+            "file" => Some(PathBase::File),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with texts missing a translation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "config", serde(rename_all = "lowercase"))]
+#[cfg_attr(not(feature = "config"), allow(dead_code))]
+pub enum MissingTranslations {
+    /// Silently use the text of the template
+    Ignore,
+    /// Emit a warning attached to the macro call
+    Warn,
+    /// Fail compilation
+    Deny,
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "config", derive(serde::Serialize))]
 pub struct Config {
@@ -25,6 +75,10 @@ pub struct Config {
     /// Where to look for templates (relative to crate root)
     pub template_directory: String,
 
+    /// What template paths are relative to: "crate" for the template directory, or "file" for the directory of the Rust file containing the macro call.
+    /// Can be overridden per call with `template_rel = "crate"` or `template_rel = "file"`.
+    pub templates_relative_to: PathBase,
+
     /// Where to look for locales (relative to crate root)
     pub locale_directory: String,
 
@@ -32,9 +86,40 @@ pub struct Config {
     /// If will be inserted in generated code like this: `match locale_code {`.
     pub locale_code: String,
 
+    /// Name of the locale of the texts written in templates.
+    /// Locales without translations fall back to it, after trying their language without region (`fr` for `fr-CA`).
+    pub default_locale: String,
+
+    /// Rust code evaluating to the component link used by `on:` event handlers.
+    /// It will be inserted in generated code like this: `(link_code).callback(...)`.
+    pub link_code: String,
+
     /// Two strings marking the beginning and end of a variable in a template.
     pub variable_bounds: (String, String),
 
+    /// Attributes whose values are shown to users, and are therefore translated.
+    pub translatable_attributes: Vec<String>,
+
+    /// Whether to use translations flagged as fuzzy in PO files. They are ignored by default, as they need to be reviewed.
+    pub include_fuzzy: bool,
+
+    /// How to handle whitespace in texts: "preserve", "collapse" or "trim".
+    pub whitespace: Whitespace,
+
+    /// Whether to check the scheme of URLs inserted in attributes such as `href` or `src` at runtime.
+    /// Can be overridden by the `safe-urls` and `unsafe-urls` element attributes.
+    pub safe_urls: bool,
+
+    /// URL schemes allowed in safe URL mode. Relative URLs are always allowed.
+    pub safe_url_schemes: Vec<String>,
+
+    /// What to do with texts missing a translation, or whose translation is rejected: "ignore", "warn" or "deny".
+    /// Warnings are attached to the macro call, but only nightly compilers display them.
+    pub missing_translations: MissingTranslations,
+
+    /// Overrides of `missing_translations` for some locales, such as `{ fr = "deny" }`.
+    pub missing_translations_per_locale: HashMap<String, MissingTranslations>,
+
     /// Helpers to use in templates
     #[cfg_attr(feature = "config", serde(skip_serializing))]
     pub helpers: HashMap<String, HashMap<usize, Helper>>,
@@ -45,28 +130,83 @@ impl Default for Config {
         let mut helpers = HashMap::new();
         helpers.insert(String::from("loud"), vec![Helper::parse("[0].to_uppercase()")].into_iter().collect());
         helpers.insert(String::from("message"), vec![Helper::parse("ctx.link().callback(|_| [0])")].into_iter().collect());
+        helpers.insert(String::from("iter"), vec![Helper::parse("[0].iter()")].into_iter().collect());
 
         Self {
             auto_default: false,
             template_directory: String::from("./"),
+            templates_relative_to: PathBase::Crate,
             locale_directory: String::from("./locales/"),
             locale_code: String::from("locale.as_str()"),
+            default_locale: String::from("en"),
+            link_code: String::from("ctx.link()"),
             variable_bounds: (String::from("{{"), String::from("}}")),
+            translatable_attributes: vec![String::from("placeholder"), String::from("title"), String::from("alt"), String::from("aria-label")],
+            include_fuzzy: false,
+            missing_translations: MissingTranslations::Warn,
+            missing_translations_per_locale: HashMap::new(),
+            whitespace: Whitespace::Trim,
+            safe_urls: false,
+            safe_url_schemes: vec![String::from("http"), String::from("https"), String::from("mailto"), String::from("tel")],
             helpers,
         }
     }
 }
 
+#[derive(Default)]
 #[cfg_attr(feature = "config", derive(serde::Deserialize))]
 pub struct ConfigLoader {
     pub auto_default: Option<bool>,
     pub template_directory: Option<String>,
+    pub templates_relative_to: Option<PathBase>,
     pub locale_directory: Option<String>,
     pub locale_code: Option<String>,
+    pub default_locale: Option<String>,
+    pub link_code: Option<String>,
     pub variable_separator: Option<(String, String)>,
+    pub translatable_attributes: Option<Vec<String>>,
+    pub include_fuzzy: Option<bool>,
+    pub missing_translations: Option<MissingTranslations>,
+    pub missing_translations_per_locale: Option<HashMap<String, MissingTranslations>>,
+    pub whitespace: Option<Whitespace>,
+    pub safe_urls: Option<bool>,
+    pub safe_url_schemes: Option<Vec<String>>,
     pub helpers: Option<HashMap<String, AnyValues>>,
 }
 
+#[cfg(feature = "config")]
+impl ConfigLoader {
+    /// Fills the settings missing from this config with those of a more general config
+    pub fn or(self, base: ConfigLoader) -> ConfigLoader {
+        let helpers = match (base.helpers, self.helpers) {
+            (Some(mut helpers), Some(overrides)) => {
+                helpers.extend(overrides);
+                Some(helpers)
+            }
+            (helpers, overrides) => overrides.or(helpers),
+        };
+
+        ConfigLoader {
+            auto_default: self.auto_default.or(base.auto_default),
+            template_directory: self.template_directory.or(base.template_directory),
+            templates_relative_to: self.templates_relative_to.or(base.templates_relative_to),
+            locale_directory: self.locale_directory.or(base.locale_directory),
+            locale_code: self.locale_code.or(base.locale_code),
+            default_locale: self.default_locale.or(base.default_locale),
+            link_code: self.link_code.or(base.link_code),
+            variable_separator: self.variable_separator.or(base.variable_separator),
+            translatable_attributes: self.translatable_attributes.or(base.translatable_attributes),
+            include_fuzzy: self.include_fuzzy.or(base.include_fuzzy),
+            missing_translations: self.missing_translations.or(base.missing_translations),
+            missing_translations_per_locale: self.missing_translations_per_locale.or(base.missing_translations_per_locale),
+            whitespace: self.whitespace.or(base.whitespace),
+            safe_urls: self.safe_urls.or(base.safe_urls),
+            safe_url_schemes: self.safe_url_schemes.or(base.safe_url_schemes),
+            helpers,
+        }
+    }
+}
+
 impl From<ConfigLoader> for Config {
     fn from(val: ConfigLoader) -> Self {
         let default = Config::default();
@@ -83,23 +223,83 @@ impl From<ConfigLoader> for Config {
         Config {
             auto_default: val.auto_default.unwrap_or(default.auto_default),
             template_directory: val.template_directory.unwrap_or(default.template_directory),
+            templates_relative_to: val.templates_relative_to.unwrap_or(default.templates_relative_to),
             locale_directory: val.locale_directory.unwrap_or(default.locale_directory),
             locale_code: val.locale_code.unwrap_or(default.locale_code),
+            default_locale: val.default_locale.unwrap_or(default.default_locale),
+            link_code: val.link_code.unwrap_or(default.link_code),
             variable_bounds: val.variable_separator.unwrap_or(default.variable_bounds),
+            translatable_attributes: val.translatable_attributes.unwrap_or(default.translatable_attributes),
+            include_fuzzy: val.include_fuzzy.unwrap_or(default.include_fuzzy),
+            missing_translations: val.missing_translations.unwrap_or(default.missing_translations),
+            missing_translations_per_locale: val.missing_translations_per_locale.unwrap_or(default.missing_translations_per_locale),
+            whitespace: val.whitespace.unwrap_or(default.whitespace),
+            safe_urls: val.safe_urls.unwrap_or(default.safe_urls),
+            safe_url_schemes: val.safe_url_schemes.unwrap_or(default.safe_url_schemes),
             helpers,
         }
     }
 }
 
-#[cfg(feature = "config")]
-pub fn read_config() -> Config {
-    let Ok(data) = std::fs::read_to_string("yew-template.toml") else {
-        return Config::default();
+/// Returns the path of a file of the crate being compiled, given its path relative to the crate root
+pub(crate) fn crate_path(path: impl AsRef<Path>) -> PathBuf {
+    match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(crate_root) => Path::new(&crate_root).join(path),
+        None => path.as_ref().to_path_buf(),
+    }
+}
+
+/// Checks whether a Cargo manifest defines a workspace
+fn is_workspace_manifest(path: &Path) -> bool {
+    let Ok(data) = std::fs::read_to_string(path) else {
+        return false;
     };
+    data.lines().map(|line| line.trim()).any(|line| line.starts_with("[workspace]") || line.starts_with("[workspace."))
+}
+
+/// Returns the paths of the config files that apply to the crate being compiled, from the most specific to the most general.
+pub(crate) fn config_paths() -> Vec<PathBuf> {
+    config_paths_of(&crate_path(""))
+}
+
+/// Returns the paths of the config files that apply to the crate at `crate_root`, from the most specific to the most general.
+/// These are the config file at the crate root and, for workspace members, the closest one up to the workspace root.
+fn config_paths_of(crate_root: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let crate_config = crate_root.join("yew-template.toml");
+    if crate_config.is_file() {
+        paths.push(crate_config);
+    }
 
-    let Ok(config_loader) = toml::from_str::<ConfigLoader>(&data) else {
-        abort_call_site!("Failed to parse yew-template.toml");
+    // Crates defining their own workspace don't inherit settings
+    if is_workspace_manifest(&crate_root.join("Cargo.toml")) {
+        return paths;
+    }
+    let Some(workspace_root) = crate_root.ancestors().skip(1).find(|dir| is_workspace_manifest(&dir.join("Cargo.toml"))) else {
+        return paths;
     };
+    let parent_config = crate_root.ancestors().skip(1)
+        .take_while(|dir| dir.starts_with(workspace_root))
+        .map(|dir| dir.join("yew-template.toml"))
+        .find(|path| path.is_file());
+    paths.extend(parent_config);
+    paths
+}
+
+#[cfg(feature = "config")]
+pub fn read_config() -> Config {
+    let mut config_loader = ConfigLoader::default();
+    for path in config_paths() {
+        let Ok(data) = std::fs::read_to_string(&path) else {
+            abort_call_site!("Failed to read {}", path.display());
+        };
+        let Ok(loader) = toml::from_str::<ConfigLoader>(&data) else {
+            abort_call_site!("Failed to parse {}", path.display());
+        };
+        #[cfg(feature = "log")]
+        println!("Using config at {}", path.display());
+        config_loader = config_loader.or(loader);
+    }
     let mut config: Config = config_loader.into();
 
     if !config.template_directory.is_empty() && !config.template_directory.ends_with('/') {
@@ -114,8 +314,9 @@ pub fn read_config() -> Config {
 
 #[cfg(not(feature = "config"))]
 pub fn read_config() -> Config {
-    if std::fs::File::open("yew-template.toml").is_ok() {
-        abort_call_site!("yew-template.toml found but the \"config\" feature is not enabled");
+    let path = crate_path("yew-template.toml");
+    if path.is_file() {
+        abort_call_site!("{} found but the \"config\" feature is not enabled", path.display());
     }
     Config::default()
 }
@@ -125,3 +326,25 @@ pub fn read_config() -> Config {
 fn print_default_config() {
     println!("{}", toml::to_string_pretty(&Config::default()).unwrap());
 }
+
+#[test]
+fn test_config_paths_of() {
+    let root = std::env::temp_dir().join(format!("yew-template-config-{}", std::process::id()));
+    let workspace = root.join("workspace");
+    let member = workspace.join("crates").join("app");
+    std::fs::create_dir_all(&member).unwrap();
+    std::fs::write(root.join("yew-template.toml"), "").unwrap();
+    std::fs::write(workspace.join("Cargo.toml"), "[workspace]\nmembers = [\"crates/app\"]\n").unwrap();
+    std::fs::write(member.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+
+    // Configs outside of the workspace are ignored
+    assert_eq!(config_paths_of(&member), Vec::<PathBuf>::new());
+    assert_eq!(config_paths_of(&workspace), Vec::<PathBuf>::new());
+
+    std::fs::write(workspace.join("yew-template.toml"), "").unwrap();
+    std::fs::write(member.join("yew-template.toml"), "").unwrap();
+    assert_eq!(config_paths_of(&member), vec![member.join("yew-template.toml"), workspace.join("yew-template.toml")]);
+    assert_eq!(config_paths_of(&workspace), vec![workspace.join("yew-template.toml")]);
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
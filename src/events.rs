@@ -0,0 +1,125 @@
+use crate::*;
+
+/// Properties of the event target that can be extracted in event handlers
+const TARGET_PROPERTIES: [&str; 2] = ["value", "checked"];
+
+/// Returns the Yew event type passed to listeners of the given event
+fn event_type(event: &str) -> &'static str {
+    match event {
+        "click" | "dblclick" | "contextmenu" | "auxclick" | "mousedown" | "mouseup" | "mouseenter" | "mouseleave" | "mousemove" | "mouseout" | "mouseover" => "yew::events::MouseEvent",
+        "pointerdown" | "pointerup" | "pointermove" | "pointerenter" | "pointerleave" | "pointerover" | "pointerout" | "pointercancel" => "yew::events::PointerEvent",
+        "keydown" | "keyup" | "keypress" => "yew::events::KeyboardEvent",
+        "focus" | "blur" | "focusin" | "focusout" => "yew::events::FocusEvent",
+        "drag" | "dragstart" | "dragend" | "dragenter" | "dragleave" | "dragover" | "drop" => "yew::events::DragEvent",
+        "touchstart" | "touchend" | "touchmove" | "touchcancel" => "yew::events::TouchEvent",
+        "input" => "yew::events::InputEvent",
+        "submit" => "yew::events::SubmitEvent",
+        "wheel" => "yew::events::WheelEvent",
+        _ => "yew::events::Event",
+    }
+}
+
+/// Returns the Rust code reading a property of the target of the event `e`
+fn target_property_code(property: &str) -> String {
+    format!("yew::TargetCast::target_unchecked_into::<web_sys::HtmlInputElement>(&e).{property}()")
+}
+
+/// Checks whether a Rust expression mentions the given identifier
+pub(crate) fn mentions_ident(code: &str, ident: &str) -> bool {
+    code.match_indices(ident).any(|(idx, _)| {
+        let before = code[..idx].chars().next_back();
+        let after = code[idx + ident.len()..].chars().next();
+        !before.map(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == ':').unwrap_or(false)
+            && !after.map(|c| c.is_alphanumeric() || c == '_').unwrap_or(false)
+    })
+}
+
+/// Turns an `on:event.modifier="Msg::Variant"` attribute to Rust code for Yew
+pub(crate) fn event_attr_to_code(name: &str, value: &str, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> String {
+    let mut modifiers = name.trim_start_matches("on:").split('.');
+    let event = modifiers.next().unwrap_or_default();
+    if event.is_empty() {
+        abort!(args.path_span, "Missing event name in {:?} attribute", name);
+    }
+
+    // Build the message expression, replacing template variables with their values
+    let mut used_properties = Vec::new();
+    let message = TextPart::parse(value, args).into_iter().map(|part| match part {
+        TextPart::Literal(code) => {
+            used_properties.extend(TARGET_PROPERTIES.iter().filter(|p| mentions_ident(&code, p)));
+            code
+        },
+        TextPart::Expression(_) => part.to_code(opts, iters, args),
+    }).collect::<String>();
+    if message.trim().is_empty() {
+        abort!(args.path_span, "Missing message in {:?} attribute", name);
+    }
+    used_properties.sort();
+    used_properties.dedup();
+
+    // Generate the statements running before the message is sent
+    let mut statements = String::new();
+    for modifier in modifiers {
+        match modifier {
+            "prevent" => statements.push_str("e.prevent_default(); "),
+            "stop" => statements.push_str("e.stop_propagation(); "),
+            modifier => abort!(args.path_span, "Unknown event modifier {:?} in {:?} attribute (expected prevent or stop)", modifier, name),
+        }
+    }
+    for property in used_properties {
+        statements.push_str(&format!("let {property} = {}; ", target_property_code(property)));
+    }
+
+    let link_code = &args.config.link_code;
+    let closure = match statements.is_empty() {
+        true => format!("move |_| {message}"),
+        false => format!("move |e: {}| {{ {statements}{message} }}", event_type(event)),
+    };
+    format!("on{event}={{({link_code}).callback({closure})}}")
+}
+
+/// Removes `bind:` attributes from an element and turns them to Rust code for Yew
+pub(crate) fn bind_attrs_to_code(attrs: &mut Vec<(String, String)>, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> Vec<String> {
+    let take_attr = |attrs: &mut Vec<(String, String)>, name: &str| {
+        let idx = attrs.iter().position(|(n, _)| n == name)?;
+        Some(attrs.remove(idx).1)
+    };
+    let msg = take_attr(attrs, "bind:msg");
+    let setter = take_attr(attrs, "bind:setter");
+    let mut bindings = TARGET_PROPERTIES.iter().filter_map(|property| take_attr(attrs, &format!("bind:{property}")).map(|value| (*property, value))).collect::<Vec<_>>();
+    if let Some((name, _)) = attrs.iter().find(|(n, _)| n.starts_with("bind:")) {
+        abort!(args.path_span, "Unknown binding {:?} (expected bind:value or bind:checked)", name);
+    }
+
+    let (property, value) = match bindings.len() {
+        0 if msg.is_some() || setter.is_some() => abort!(args.path_span, "bind:msg and bind:setter require a bind:value or bind:checked attribute"),
+        0 => return Vec::new(),
+        1 => bindings.remove(0),
+        _ => abort!(args.path_span, "Elements can only have one binding"),
+    };
+    if !matches!(TextPart::parse(&value, args).as_slice(), [TextPart::Expression(_)]) {
+        abort!(args.path_span, "bind:{} attribute must be a variable", property);
+    }
+    let event = match property {
+        "checked" => "change",
+        _ => "input",
+    };
+
+    let mut codes = Vec::new();
+    codes.extend(attr_to_code((property.to_string(), value), opts, iters, args));
+    match (msg, setter) {
+        (Some(msg), None) => codes.push(event_attr_to_code(&format!("on:{event}"), &format!("{msg}({property})"), opts, iters, args)),
+        (None, Some(setter)) => {
+            let [TextPart::Expression(id)] = &TextPart::parse(&setter, args)[..] else {
+                abort!(args.path_span, "bind:setter attribute must be a variable");
+            };
+            let setter = args.get_val(id, opts, iters, args);
+            let event_type = event_type(event);
+            let property_code = target_property_code(property);
+            codes.push(format!("on{event}={{{{let setter = ({setter}).clone(); yew::Callback::from(move |e: {event_type}| setter.emit({property_code}))}}}}"));
+        },
+        (None, None) => abort!(args.path_span, "bind:{} requires either a bind:msg or a bind:setter attribute", property),
+        (Some(_), Some(_)) => abort!(args.path_span, "bind:msg and bind:setter cannot be used together"),
+    }
+    codes
+}
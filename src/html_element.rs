@@ -1,8 +1,10 @@
 use crate::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) enum HtmlPart {
     Text(String),
+    /// Text of raw-text or preformatted elements, which is never translated nor trimmed
+    RawText { text: String, interpolate: bool },
     Element(Element),
 }
 
@@ -12,17 +14,18 @@ impl HtmlPart {
         match self {
             HtmlPart::Element(el) => element_to_code(el, depth, opts, iters, args),
             HtmlPart::Text(text) => text_to_code(text, depth, opts, iters, args),
+            HtmlPart::RawText { text, interpolate } => raw_text_to_code(text, interpolate, depth, opts, iters, args),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct HtmlPartWithLine {
     pub(crate) part: HtmlPart,
     pub(crate) line: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct Element {
     pub(crate) name: String,
     pub(crate) self_closing: bool,
@@ -32,40 +35,168 @@ pub(crate) struct Element {
 }
 
 impl Element {
-    pub(crate) fn clean_text(&mut self) {
-        let mut new_children = Vec::new();
-        let mut current_text = String::new();
-        let mut current_line = None;
+    /// Adds a marker attribute to the child elements that don't have any of the overriding attributes
+    pub(crate) fn propagate_marker(&mut self, marker: &str, overrides: &[&str]) {
+        for child in &mut self.children {
+            if let HtmlPart::Element(el) = &mut child.part {
+                if !el.open_attrs.iter().any(|(n, _)| overrides.contains(&n.as_str())) {
+                    el.open_attrs.push((marker.to_string(), String::new()));
+                }
+            }
+        }
+    }
+
+    /// Returns the names of the node refs declared in this element and its children
+    #[cfg(feature = "i18n")]
+    pub(crate) fn get_node_refs(&self) -> Vec<String> {
+        let mut node_refs = self.open_attrs.iter().filter(|(n, v)| n == "ref" && is_ref_name(v)).map(|(_, v)| v.to_owned()).collect::<Vec<_>>();
+        for child in &self.children {
+            if let HtmlPart::Element(el) = &child.part {
+                node_refs.append(&mut el.get_node_refs());
+            }
+        }
+        node_refs
+    }
+
+    /// Checks whether the element opts its content out of translation with `translate="no"` or `notranslate`
+    pub(crate) fn is_untranslated(&self) -> bool {
+        self.open_attrs.iter().any(|(n, v)| n == "notranslate" || (n == "translate" && v == "no"))
+    }
+
+    /// Checks whether the element is a raw-text or preformatted element, whose content must be kept as is
+    pub(crate) fn is_raw(&self) -> bool {
+        matches!(self.name.as_str(), "style" | "script" | "pre" | "textarea")
+    }
+
+    /// Turns the text of this element and its children into raw text, preserving whitespace
+    fn mark_raw(&mut self, interpolate: bool) {
+        let interpolate = interpolate || self.open_attrs.iter().any(|(n, _)| n == "interpolate");
+        let mut new_children: Vec<HtmlPartWithLine> = Vec::new();
         for child in self.children.drain(..) {
             match child.part {
-                HtmlPart::Text(text) => {
-                    current_text.push_str(&text);
-                    if current_line.is_none() {
-                        current_line = Some(child.line);
-                    }
+                HtmlPart::Text(text) | HtmlPart::RawText { text, .. } => match new_children.last_mut() {
+                    Some(HtmlPartWithLine { part: HtmlPart::RawText { text: previous_text, .. }, .. }) => previous_text.push_str(&text),
+                    _ => new_children.push(HtmlPartWithLine { part: HtmlPart::RawText { text, interpolate }, line: child.line }),
+                },
+                HtmlPart::Element(mut element) => {
+                    element.mark_raw(interpolate);
+                    new_children.push(HtmlPartWithLine { part: HtmlPart::Element(element), line: child.line });
+                }
+            }
+        }
+
+        // Like browsers, ignore the newline following the opening tag
+        if self.name == "pre" || self.name == "textarea" {
+            if let Some(HtmlPartWithLine { part: HtmlPart::RawText { text, .. }, .. }) = new_children.first_mut() {
+                if text.starts_with('\n') {
+                    text.remove(0);
                 }
+            }
+        }
+        new_children.retain(|c| !matches!(&c.part, HtmlPart::RawText { text, .. } if text.is_empty()));
+
+        self.children = new_children;
+    }
+
+    pub(crate) fn clean_text(&mut self, whitespace: Whitespace, args: &Args) {
+        if self.is_raw() {
+            self.mark_raw(false);
+            return;
+        }
+
+        // Keep the spaces around inline elements of whole-sentence translation units
+        let whitespace = match whitespace {
+            Whitespace::Trim if self.open_attrs.iter().any(|(n, _)| n == "i18n") => Whitespace::Collapse,
+            whitespace => whitespace,
+        };
+
+        // Merge adjacent texts
+        let mut new_children: Vec<HtmlPartWithLine> = Vec::new();
+        for child in self.children.drain(..) {
+            match child.part {
+                HtmlPart::Text(text) => match new_children.last_mut() {
+                    Some(HtmlPartWithLine { part: HtmlPart::Text(previous_text), .. }) => previous_text.push_str(&text),
+                    _ => new_children.push(HtmlPartWithLine { part: HtmlPart::Text(text), line: child.line }),
+                },
+                HtmlPart::RawText { .. } => unreachable!("raw text is only created when cleaning text"),
                 HtmlPart::Element(mut element) => {
-                    current_text = current_text.trim_matches(|c: char| (c.is_whitespace() || c == '\n') && c != '\u{A0}').to_string();
-                    if !current_text.is_empty() {
-                        new_children.push(HtmlPartWithLine {
-                            part: HtmlPart::Text(current_text),
-                            line: current_line.unwrap(),
-                        });
-                        current_text = String::new();
-                        current_line = None;
-                    }
-                    element.clean_text();
+                    element.clean_text(whitespace, args);
                     new_children.push(HtmlPartWithLine { part: HtmlPart::Element(element), line: child.line });
                 }
             }
         }
-        current_text = current_text.trim_matches(|c: char| (c.is_whitespace() || c == '\n') && c != '\u{A0}').to_string();
-        if !current_text.is_empty() {
-            new_children.push(HtmlPartWithLine {
-                part: HtmlPart::Text(current_text),
-                line: current_line.unwrap(),
-            });
+
+        // Remove template comments
+        for child in new_children.iter_mut() {
+            if let HtmlPart::Text(text) = &mut child.part {
+                *text = TextPart::strip_comments(text, args);
+            }
+        }
+
+        // Handle whitespace
+        let is_space = |c: char| c.is_whitespace() && c != '\u{A0}';
+        let children_count = new_children.len();
+        for (i, child) in new_children.iter_mut().enumerate() {
+            let HtmlPart::Text(text) = &mut child.part else { continue };
+            match whitespace {
+                Whitespace::Preserve => (),
+                Whitespace::Collapse => {
+                    let mut collapsed = String::with_capacity(text.len());
+                    for c in text.chars() {
+                        match is_space(c) {
+                            true if collapsed.ends_with(' ') => (),
+                            true => collapsed.push(' '),
+                            false => collapsed.push(c),
+                        }
+                    }
+                    if i == 0 {
+                        collapsed = collapsed.trim_start_matches(is_space).to_string();
+                    }
+                    if i + 1 == children_count {
+                        collapsed.truncate(collapsed.trim_end_matches(is_space).len());
+                    }
+                    *text = collapsed;
+                },
+                Whitespace::Trim => *text = text.trim_matches(is_space).to_string(),
+            }
+        }
+        new_children.retain(|c| !matches!(&c.part, HtmlPart::Text(text) if text.is_empty()));
+
+        // Merge plural-other elements into the preceding plural element
+        let mut i = 0;
+        while i < new_children.len() {
+            let HtmlPart::Element(el) = &new_children[i].part else { i += 1; continue };
+            if !el.open_attrs.iter().any(|(n, _)| n == "plural-other") || el.open_attrs.iter().any(|(n, _)| n == "plural") {
+                i += 1;
+                continue;
+            }
+            let HtmlPart::Element(other) = new_children.remove(i).part else { unreachable!() };
+            let other_text = match other.children.as_slice() {
+                [HtmlPartWithLine { part: HtmlPart::Text(text), .. }] => text.to_owned(),
+                _ => abort!(args.path_span, "plural-other elements must only contain text in template {}", args.path),
+            };
+            while i > 0 && matches!(&new_children[i - 1].part, HtmlPart::Text(text) if text.trim().is_empty()) {
+                new_children.remove(i - 1);
+                i -= 1;
+            }
+            match i.checked_sub(1).map(|i| &mut new_children[i].part) {
+                Some(HtmlPart::Element(el)) if el.name == other.name && el.open_attrs.iter().any(|(n, _)| n == "plural") && !el.open_attrs.iter().any(|(n, _)| n == "plural-other") => {
+                    el.open_attrs.push((String::from("plural-other"), other_text));
+                },
+                _ => abort!(args.path_span, "plural-other elements must follow an element of the same kind with a plural attribute in template {}", args.path),
+            }
         }
+
         self.children = new_children;
     }
+
+    /// Returns the singular and plural forms of the text of an element with a `plural` attribute
+    pub(crate) fn get_plural_forms(&self) -> Option<(String, String)> {
+        let singular = match self.children.as_slice() {
+            [HtmlPartWithLine { part: HtmlPart::Text(text), .. }] => text.to_owned(),
+            _ => return None,
+        };
+        let plural = self.open_attrs.iter().find(|(n, _)| n == "plural-other").map(|(_, v)| v.to_owned())?;
+        Some((singular, plural))
+    }
 }
@@ -1,62 +1,226 @@
-use std::{collections::HashMap, path::Path, io::Read};
-use poreader::{PoParser, Message};
+use std::{collections::{HashMap, HashSet}, path::{Path, PathBuf}, io::Read, sync::{Mutex, OnceLock}};
+use poreader::{PoParser, Message, CatalogueReader};
 use crate::*;
 
+mod plural_forms;
+pub(crate) use plural_forms::*;
+
 pub struct Translatable {
     original: String,
+    /// Plural form of the original text, for texts depending on a count
+    plural: Option<String>,
     origin: (String, usize),
     context: String,
+    /// Comment for translators, from the `i18n-note` attribute of an enclosing element
+    note: Option<String>,
 }
 
 fn context_from_path(path: &str) -> &str {
-    path.split('/').last().unwrap_or_default().trim_end_matches(".html")
+    path.split('/').next_back().unwrap_or_default().trim_end_matches(".html")
+}
+
+/// Returns the context of the texts of the template, which is specific to the template section if any
+fn default_context(args: &Args) -> String {
+    match &args.section {
+        Some(section) => format!("{}#{section}", context_from_path(&args.path)),
+        None => context_from_path(&args.path).to_string(),
+    }
+}
+
+/// Returns the context of the texts being generated, set by the closest enclosing `i18n-context` attribute if any
+fn current_context(args: &Args) -> String {
+    args.i18n_contexts.borrow().last().cloned().unwrap_or_else(|| default_context(args))
+}
+
+/// Checks whether a text needs to be translated, which is not the case of whitespace and single variables
+pub(crate) fn is_translatable(text: &str, args: &Args) -> bool {
+    !text.trim().is_empty() && !matches!(TextPart::parse(text, args).as_slice(), &[TextPart::Expression(_)])
 }
 
 impl Element {
-    pub(crate) fn get_translatables(&self, args: &Args) -> Vec<Translatable> {
+    /// Returns the content of an element with an `i18n` attribute as a single text, where inline elements are replaced with numbered placeholders such as `<0>here</0>`.
+    /// The inline elements are appended to `placeholders`, in the order of their numbers.
+    pub(crate) fn markup_msgid<'a>(&'a self, placeholders: &mut Vec<&'a Element>, args: &Args) -> String {
+        let mut msgid = String::new();
+        for child in &self.children {
+            match &child.part {
+                HtmlPart::Text(text) => msgid.push_str(text),
+                HtmlPart::RawText { .. } => abort!(args.path_span, "Elements with an i18n attribute cannot contain raw-text or preformatted elements in template {}", args.path),
+                HtmlPart::Element(el) => {
+                    let number = placeholders.len();
+                    placeholders.push(el);
+                    match el.children.is_empty() {
+                        true => msgid.push_str(&format!("<{number}/>")),
+                        false => msgid.push_str(&format!("<{number}>{}</{number}>", el.markup_msgid(placeholders, args))),
+                    }
+                }
+            }
+        }
+        msgid
+    }
+
+    /// Returns the translatable attributes of this element and its children
+    fn attribute_translatables(&self, line: usize, context: &str, note: Option<&str>, args: &Args) -> Vec<Translatable> {
         let mut translatables = Vec::new();
+        for (name, value) in &self.open_attrs {
+            if !args.config.translatable_attributes.contains(name) || !is_translatable(value, args) {
+                continue;
+            }
+            translatables.push(Translatable {
+                original: value.to_owned(),
+                plural: None,
+                origin: (args.path.trim_start_matches("./").to_owned(), line + args.line_offset),
+                context: format!("{context}@{name}"),
+                note: note.map(|n| n.to_owned()),
+            });
+        }
+        translatables
+    }
+
+    /// Returns the texts to translate in this element, which starts at the given line
+    pub(crate) fn get_translatables(&self, line: usize, context: &str, note: Option<&str>, args: &Args) -> Vec<Translatable> {
+        if self.is_untranslated() {
+            return Vec::new();
+        }
+        let context = self.open_attrs.iter().find(|(n, _)| n == "i18n-context").map(|(_, v)| v.as_str()).unwrap_or(context);
+        let note = self.open_attrs.iter().find(|(n, _)| n == "i18n-note").map(|(_, v)| v.as_str()).or(note);
+        let origin = |line: usize| (args.path.trim_start_matches("./").to_owned(), line + args.line_offset);
+        let mut translatables = Vec::new();
+
+        // Attribute values shown to users
+        translatables.append(&mut self.attribute_translatables(line, context, note, args));
+
+        // Whole sentences containing inline elements
+        if self.open_attrs.iter().any(|(n, _)| n == "i18n") {
+            let mut placeholders = Vec::new();
+            let msgid = self.markup_msgid(&mut placeholders, args);
+            if is_translatable(&msgid, args) {
+                translatables.push(Translatable {
+                    original: msgid,
+                    plural: None,
+                    origin: origin(line),
+                    context: context.to_owned(),
+                    note: note.map(|n| n.to_owned()),
+                });
+            }
+            for placeholder in placeholders {
+                translatables.append(&mut placeholder.attribute_translatables(line, context, note, args));
+            }
+            return translatables;
+        }
+
+        // Texts depending on a count
+        if self.open_attrs.iter().any(|(n, _)| n == "plural") {
+            if let Some((singular, plural)) = self.get_plural_forms() {
+                translatables.push(Translatable {
+                    original: singular,
+                    plural: Some(plural),
+                    origin: origin(line),
+                    context: context.to_owned(),
+                    note: note.map(|n| n.to_owned()),
+                });
+            }
+            return translatables;
+        }
+
         for child in &self.children {
             match &child.part {
                 HtmlPart::Text(text) => {
-                    // Ignore the text if it's only a variable
-                    let text_parts = TextPart::parse(text, args);
-                    if matches!(text_parts.as_slice(), &[TextPart::Expression(_)]) {
+                    if !is_translatable(text, args) {
                         continue;
                     }
-        
                     translatables.push(Translatable {
                         original: text.to_string(),
-                        origin: (args.path.trim_start_matches("./").to_owned(), child.line),
-                        context: context_from_path(&args.path).to_string(),
+                        plural: None,
+                        origin: origin(child.line),
+                        context: context.to_owned(),
+                        note: note.map(|n| n.to_owned()),
                     })
                 },
-                HtmlPart::Element(el) => translatables.append(&mut el.get_translatables(args)),
+                HtmlPart::RawText { .. } => continue,
+                HtmlPart::Element(el) => translatables.append(&mut el.get_translatables(child.line, context, note, args)),
             }
         }
         translatables
     }
 }
 
+/// Rebuilds the content of an element with an `i18n` attribute from a translation containing numbered placeholders, such as `Cliquez <0>ici</0>`.
+/// Returns `None` if the placeholders of the translation are malformed or unknown.
+pub(crate) fn markup_to_children(translation: &str, placeholders: &[&Element], line: usize) -> Option<Vec<HtmlPartWithLine>> {
+    // Each level of the stack holds the number of an open placeholder and the content read so far
+    let mut stack: Vec<(Option<usize>, Vec<HtmlPartWithLine>)> = vec![(None, Vec::new())];
+    let mut text = String::new();
+    let mut rest = translation;
+    while let Some(c) = rest.chars().next() {
+        let tag = rest.strip_prefix('<').and_then(|tag| tag.split_once('>')).map(|(tag, _)| tag).filter(|tag| {
+            let number = tag.trim_start_matches('/').trim_end_matches('/');
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        });
+        let Some(tag) = tag else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+        rest = &rest[tag.len() + 2..];
+
+        if !text.is_empty() {
+            stack.last_mut()?.1.push(HtmlPartWithLine { part: HtmlPart::RawText { text: std::mem::take(&mut text), interpolate: true }, line });
+        }
+        let number: usize = tag.trim_matches('/').parse().ok()?;
+        let mut element = (*placeholders.get(number)?).clone();
+        if let Some(tag) = tag.strip_prefix('/') {
+            let (open_number, children) = stack.pop()?;
+            if open_number != Some(number) || tag.ends_with('/') {
+                return None;
+            }
+            element.children = children;
+        } else if tag.ends_with('/') {
+            element.children = Vec::new();
+        } else {
+            stack.push((Some(number), Vec::new()));
+            continue;
+        }
+        stack.last_mut()?.1.push(HtmlPartWithLine { part: HtmlPart::Element(element), line });
+    }
+    if !text.is_empty() {
+        stack.last_mut()?.1.push(HtmlPartWithLine { part: HtmlPart::RawText { text, interpolate: true }, line });
+    }
+
+    match stack.pop() {
+        Some((None, children)) if stack.is_empty() => Some(children),
+        _ => None,
+    }
+}
+
 impl Translatable {
     fn generate_pot_part(&self) -> String {
-        format!("#: {}:{}\nmsgctxt {:?}\nmsgid {:?}\nmsgstr \"\"", self.origin.0, self.origin.1, self.context, self.original)
+        let comments = self.note.iter().flat_map(|note| note.lines()).map(|line| format!("#. {}\n", line.trim())).collect::<String>();
+        comments + &match &self.plural {
+            Some(plural) => format!("#: {}:{}\nmsgctxt {:?}\nmsgid {:?}\nmsgid_plural {:?}\nmsgstr[0] \"\"\nmsgstr[1] \"\"", self.origin.0, self.origin.1, self.context, self.original, plural),
+            None => format!("#: {}:{}\nmsgctxt {:?}\nmsgid {:?}\nmsgstr \"\"", self.origin.0, self.origin.1, self.context, self.original),
+        }
     }
 }
 
+/// Header entry of the generated `.pot` file
+const POT_HEADER: &str = "msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n";
+
 pub(crate) fn generate_pot(root: &Element, args: &Args) {
-    let path = Path::new(&args.config.locale_directory);
-    if !path.exists() {
+    let locale_directory = crate_path(&args.config.locale_directory);
+    if !locale_directory.exists() {
         return;
     }
+    let pot_path = locale_directory.join("template.pot");
 
     // Delete template.pot if it hasn't been modified too recently (otherwise keep the data)
-    let mut data = match std::fs::File::open(format!("{}template.pot", args.config.locale_directory)) {
+    let mut data = match std::fs::File::open(&pot_path) {
         Ok(mut file) => {
             let metadata = file.metadata().unwrap();
             let mut data = String::new();
             file.read_to_string(&mut data).unwrap();
             if metadata.modified().unwrap().elapsed().unwrap().as_secs() > 120 {
-                std::fs::remove_file(format!("{}template.pot", args.config.locale_directory)).unwrap();
+                std::fs::remove_file(&pot_path).unwrap();
                 String::new()
             } else {
                 data
@@ -65,8 +229,25 @@ pub(crate) fn generate_pot(root: &Element, args: &Args) {
         _ => String::new()
     };
 
+    // Start with a header entry, so that the comments above it document the whole file rather than its first text
+    if !data.contains(POT_HEADER) {
+        data.insert_str(0, POT_HEADER);
+    }
+
+    // Document the node refs the template expects in the header comments
+    let mut node_refs = root.get_node_refs();
+    node_refs.sort();
+    node_refs.dedup();
+    if !node_refs.is_empty() {
+        let inventory = format!("# Node refs of {}: {}\n", args.path.trim_start_matches("./"), node_refs.join(", "));
+        if !data.contains(&inventory) {
+            let header_position = data.find(POT_HEADER).unwrap_or_default();
+            data.insert_str(header_position, &inventory);
+        }
+    }
+
     // Append new translatables
-    let translatables = root.get_translatables(args);
+    let translatables = root.get_translatables(1, &default_context(args), None, args);
     for translatable in translatables {
         let pot_part = translatable.generate_pot_part();
         if !data.contains(&pot_part) {
@@ -75,28 +256,78 @@ pub(crate) fn generate_pot(root: &Element, args: &Args) {
             data.push('\n');
         }
     }
-    std::fs::write(format!("{}template.pot", args.config.locale_directory), data).unwrap();
+    std::fs::write(&pot_path, data).unwrap();
 
     // Make sure the file is in .gitignore
-    let gitignore_path = format!("{}.gitignore", args.config.locale_directory);
-    let path = Path::new(&gitignore_path);
-    if !path.exists() {
+    let gitignore_path = locale_directory.join(".gitignore");
+    if !gitignore_path.exists() {
         std::fs::write(gitignore_path, "template.pot\n").unwrap();
     }
 }
 
+/// PO files whose ignored fuzzy entries were already reported, as catalogs are read again for every macro call
+static REPORTED_FUZZY_CATALOGS: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+
+/// Translations by context and source text, along with their line in the PO file
+type Translations<T> = HashMap<(String, String), (T, usize)>;
+
 #[derive(Debug)]
 pub(crate) struct Catalog {
-    catalogs: HashMap<String, HashMap<(String, String), String>>,
+    /// Translations of texts by locale
+    catalogs: HashMap<String, Translations<String>>,
+    /// Translations of texts with plural forms, by locale
+    plural_catalogs: HashMap<String, Translations<Vec<String>>>,
+    /// Rust code of the plural formula of each locale, computing the plural form index from `macro_produced_count`, or the formula if it is unsupported
+    plural_formulas: HashMap<String, Result<String, String>>,
+    /// Paths of the PO files the catalogs were read from, by locale
+    pub(crate) files: HashMap<String, PathBuf>,
+}
+
+/// Returns the line numbers of the units of a PO file, in the order they are read, excluding the header
+fn unit_lines(data: &str) -> Vec<usize> {
+    let lines = data.lines().collect::<Vec<_>>();
+    let mut unit_lines = lines.iter().enumerate()
+        .filter(|(_, line)| line.starts_with("msgid ") || line.starts_with("#~ msgid "))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    // The header has an empty msgid directly followed by its msgstr
+    if let Some(&first) = unit_lines.first() {
+        if lines[first].trim_end() == "msgid \"\"" && lines.get(first + 1).map(|l| l.starts_with("msgstr")).unwrap_or(false) {
+            unit_lines.remove(0);
+        }
+    }
+    unit_lines.into_iter().map(|i| i + 1).collect()
+}
+
+/// Returns the variables a text refers to
+fn variables(text: &str, args: &Args) -> Vec<String> {
+    TextPart::parse(text, args).into_iter().filter_map(|part| match part {
+        TextPart::Expression(id) => Some(id),
+        TextPart::Literal(_) => None,
+    }).collect()
+}
+
+/// Reports a text left untranslated in a locale, because its translation is missing or rejected, depending on the `missing_translations` policy of the locale
+pub(crate) fn report_untranslated(message: String, language: &str, args: &Args) {
+    let policy = args.config.missing_translations_per_locale.get(language).copied().unwrap_or(args.config.missing_translations);
+    match policy {
+        MissingTranslations::Ignore => (),
+        MissingTranslations::Warn => emit_call_site_warning!("{}", message),
+        MissingTranslations::Deny => args.missing_translations.borrow_mut().push(message),
+    }
 }
 
 impl Catalog {
-    pub(crate) fn new(locale_directory: &str) -> Self {
+    pub(crate) fn new(locale_directory: &Path, include_fuzzy: bool) -> Self {
         // Read all PO files in the locale_directory
         let mut catalogs = HashMap::new();
+        let mut plural_catalogs = HashMap::new();
+        let mut plural_formulas = HashMap::new();
+        let mut files = HashMap::new();
         let read_dir = match std::fs::read_dir(locale_directory) {
             Ok(read_dir) => read_dir,
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self { catalogs },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self { catalogs, plural_catalogs, plural_formulas, files },
             Err(_) => abort_call_site!("Failed to read locale directory"),
         };
         for entry in read_dir {
@@ -108,45 +339,159 @@ impl Catalog {
 
             let locale = path.file_name().expect("no file stem").to_str().expect("cannot convert file stem").trim_end_matches(".po").to_string();
             let locale = locale.replace('\\', "\\\\").replace('\"', "\\\"");
-            let file = std::fs::File::open(path).unwrap_or_else(|_| panic!("could not open the {locale} catalog"));
+            let data = std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("could not open the {locale} catalog"));
             let parser = PoParser::new();
-            let reader = parser.parse(file).unwrap_or_else(|_| panic!("could not parse the {locale} catalog"));
+            let reader = parser.parse(data.as_bytes()).unwrap_or_else(|_| panic!("could not parse the {locale} catalog"));
+            let mut lines = unit_lines(&data).into_iter();
+
+            // Read the plural formula from the Plural-Forms header, such as "nplurals=2; plural=(n > 1);"
+            let plural_formula = reader.header_properties().get("Plural-Forms").and_then(|forms| forms.split(';').find_map(|f| f.trim().strip_prefix("plural=")).map(|f| f.to_owned()));
+            if let Some(plural_formula) = plural_formula {
+                let code = plural_formula_to_code(&plural_formula, "macro_produced_count").ok_or(plural_formula);
+                plural_formulas.insert(locale.to_string(), code);
+            }
 
             let mut items = HashMap::new();
+            let mut plural_items = HashMap::new();
+            let mut fuzzy_count = 0;
             for unit in reader {
+                let line = lines.next().unwrap_or_default();
                 let Ok(unit) = unit else {
                     eprintln!("WARNING: Invalid unit in the {locale} catalog");
                     continue;
                 };
+                if unit.is_obsolete() {
+                    continue;
+                }
+                if !include_fuzzy && unit.flags().contains("fuzzy") {
+                    fuzzy_count += 1;
+                    continue;
+                }
                 let context = unit.context().unwrap_or("").to_string();
-                if let Message::Simple { id, text: Some(text) } = unit.message() {
-                    items.insert((context, id.to_owned()), text.to_owned());
+                match unit.message() {
+                    Message::Simple { id, text: Some(text) } => { items.insert((context, id.to_owned()), (text.to_owned(), line)); },
+                    Message::Plural(plural) if !plural.is_blank() => { plural_items.insert((context, plural.singular().to_owned()), (plural.values().to_owned(), line)); },
+                    _ => (),
                 }
             }
+            let reported_catalogs = REPORTED_FUZZY_CATALOGS.get_or_init(Default::default);
+            if fuzzy_count > 0 && reported_catalogs.lock().map(|mut reported| reported.insert(path.clone())).unwrap_or(false) {
+                emit_call_site_warning!("Ignored {} fuzzy translation(s) in the {} catalog", fuzzy_count, locale);
+            }
         
             catalogs.insert(locale.to_string(), items);
+            plural_catalogs.insert(locale.to_string(), plural_items);
+            files.insert(locale.to_string(), path);
         }
     
         Self {
             catalogs,
+            plural_catalogs,
+            plural_formulas,
+            files,
         }
     }
 
     pub(crate) fn translate_text(&self, text: &str, args: &Args) -> Vec<(String, Vec<TextPart>)> {
-        let context = context_from_path(&args.path).to_string();
-        let context_and_text = (context.clone(), text.to_string());
+        self.translate(text, current_context(args), args)
+    }
+
+    /// Returns the translations of the value of an attribute shown to users
+    pub(crate) fn translate_attribute(&self, value: &str, name: &str, args: &Args) -> Vec<(String, Vec<TextPart>)> {
+        self.translate(value, format!("{}@{name}", current_context(args)), args)
+    }
 
+    /// Returns the names of the locales having a catalog
+    pub(crate) fn locales(&self) -> Vec<&str> {
+        let mut locales = self.catalogs.keys().map(|l| l.as_str()).collect::<Vec<_>>();
+        locales.sort();
+        locales
+    }
+
+    /// Returns the translations of the content of an element with an `i18n` attribute, containing numbered placeholders
+    pub(crate) fn translate_markup(&self, msgid: &str, args: &Args) -> Vec<(String, String)> {
+        self.translated_strings(msgid, current_context(args), args)
+    }
+
+    fn translate(&self, text: &str, context: String, args: &Args) -> Vec<(String, Vec<TextPart>)> {
         let mut translations = Vec::new();
         translations.push((String::new(), TextPart::parse(text, args)));
+        for (language, translated_text) in self.translated_strings(text, context, args) {
+            translations.push((language, TextPart::parse(&translated_text, args)));
+        }
+        translations
+    }
+
+    /// Returns the translations of a text in the locales where it is translated
+    fn translated_strings(&self, text: &str, context: String, args: &Args) -> Vec<(String, String)> {
+        let context_and_text = (context, text.to_string());
+
+        let mut translations = Vec::new();
         for (language, catalog) in &self.catalogs {
-            let Some(translated_text) = catalog.get(&context_and_text) else {
-                eprintln!("WARNING: Missing translation for text {text:?} with context {context:?} in language {language}");
+            let Some((translated_text, line)) = catalog.get(&context_and_text) else {
+                report_untranslated(format!("Missing translation for text {text:?} with context {:?} in language {language}", context_and_text.0), language, args);
+                continue;
+            };
+            if !self.check_variables(&[text], &[translated_text], language, *line, args) {
+                continue;
+            }
+            translations.push((language.to_owned(), translated_text.to_owned()));
+        }
+        translations
+    }
+
+    /// Checks that translations only refer to variables of the source text, which would otherwise be undefined.
+    /// Translations failing this check are reported and replaced with the source text.
+    fn check_variables(&self, sources: &[&str], translations: &[&String], language: &str, line: usize, args: &Args) -> bool {
+        let source_variables = sources.iter().flat_map(|s| variables(s, args)).collect::<Vec<_>>();
+        let mut unknown_variables = translations.iter().flat_map(|t| variables(t, args)).filter(|v| !source_variables.contains(v)).collect::<Vec<_>>();
+        if unknown_variables.is_empty() {
+            return true;
+        }
+        unknown_variables.dedup();
+        let path = self.files.get(language).map(|p| p.display().to_string()).unwrap_or_default();
+        report_untranslated(format!("Translation of text {:?} in language {language} ({path}:{line}) refers to variables missing from the source text: {}. Using the source text instead.", sources[0], unknown_variables.join(", ")), language, args);
+        false
+    }
+
+    /// Returns the plural formula and forms of a text depending on a count, for each locale.
+    /// The first item is the untranslated text, whose forms are the singular and plural forms.
+    pub(crate) fn translate_plural(&self, singular: &str, plural: &str, args: &Args) -> Vec<(String, Option<String>, Vec<Vec<TextPart>>)> {
+        let context = current_context(args);
+        let context_and_text = (context.clone(), singular.to_string());
+
+        let mut translations = Vec::new();
+        translations.push((String::new(), None, vec![TextPart::parse(singular, args), TextPart::parse(plural, args)]));
+        for (language, catalog) in &self.plural_catalogs {
+            let Some((translated_forms, line)) = catalog.get(&context_and_text) else {
+                report_untranslated(format!("Missing translation for text {singular:?} with context {context:?} in language {language}"), language, args);
                 continue;
             };
-            let translated_parts = TextPart::parse(translated_text, args);
-            translations.push((language.to_owned(), translated_parts));
+            if !self.check_variables(&[singular, plural], &translated_forms.iter().collect::<Vec<_>>(), language, *line, args) {
+                continue;
+            }
+            let formula = match self.plural_formulas.get(language) {
+                Some(Ok(formula)) => formula,
+                Some(Err(formula)) => {
+                    report_untranslated(format!("Unsupported plural formula {formula:?} in the {language} catalog, ignoring translation of {singular:?}"), language, args);
+                    continue;
+                }
+                None => {
+                    report_untranslated(format!("Missing Plural-Forms header in the {language} catalog, ignoring translation of {singular:?}"), language, args);
+                    continue;
+                }
+            };
+            let translated_forms = translated_forms.iter().map(|form| TextPart::parse(form, args)).collect();
+            translations.push((language.to_owned(), Some(formula.to_owned()), translated_forms));
         }
 
         translations
     }
 }
+
+#[test]
+fn test_unit_lines() {
+    let data = "msgid \"\"\nmsgstr \"\"\n\"Language: fr\\n\"\n\n#: a.html:1\nmsgid \"Hello\"\nmsgstr \"Bonjour\"\n\nmsgid \"\"\n\"Long \"\n\"text\"\nmsgstr \"Texte long\"\n\n#~ msgid \"Old\"\n#~ msgstr \"Vieux\"\n";
+    assert_eq!(unit_lines(data), vec![6, 9, 14]);
+    assert_eq!(unit_lines("msgid \"\"\n\"Long\"\nmsgstr \"Long\"\n"), vec![1]);
+}
@@ -0,0 +1,125 @@
+/// Rust code of a plural formula, and whether it evaluates to a `bool` rather than an `i64`
+struct Code(String, bool);
+
+impl Code {
+    fn int(self) -> String {
+        match self.1 {
+            true => format!("({} as i64)", self.0),
+            false => self.0,
+        }
+    }
+
+    fn bool(self) -> String {
+        match self.1 {
+            true => self.0,
+            false => format!("({} != 0)", self.0),
+        }
+    }
+}
+
+struct FormulaParser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
+    count_code: &'a str,
+}
+
+impl<'a> FormulaParser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next_if(&mut self, candidates: &[&str]) -> Option<&'a str> {
+        let token = self.peek().filter(|token| candidates.contains(token))?;
+        self.position += 1;
+        Some(token)
+    }
+
+    fn ternary(&mut self) -> Option<Code> {
+        let condition = self.binary(0)?;
+        if self.next_if(&["?"]).is_none() {
+            return Some(condition);
+        }
+        let if_true = self.ternary()?;
+        self.next_if(&[":"])?;
+        let if_false = self.ternary()?;
+        Some(Code(format!("(if {} {{ {} }} else {{ {} }})", condition.bool(), if_true.int(), if_false.int()), false))
+    }
+
+    /// Parses binary operations, by order of increasing precedence
+    fn binary(&mut self, level: usize) -> Option<Code> {
+        const LEVELS: [&[&str]; 6] = [&["||"], &["&&"], &["==", "!="], &["<", "<=", ">", ">="], &["+", "-"], &["*", "/", "%"]];
+        let Some(operators) = LEVELS.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        while let Some(operator) = self.next_if(operators) {
+            let right = self.binary(level + 1)?;
+            left = match level {
+                0 | 1 => Code(format!("({} {operator} {})", left.bool(), right.bool()), true),
+                2 | 3 => Code(format!("({} {operator} {})", left.int(), right.int()), true),
+                _ => Code(format!("({} {operator} {})", left.int(), right.int()), false),
+            };
+        }
+        Some(left)
+    }
+
+    fn unary(&mut self) -> Option<Code> {
+        match self.next_if(&["!"]) {
+            Some(_) => Some(Code(format!("(!{})", self.unary()?.bool()), true)),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Option<Code> {
+        let token = self.peek()?;
+        self.position += 1;
+        match token {
+            "n" => Some(Code(self.count_code.to_string(), false)),
+            "(" => {
+                let code = self.ternary()?;
+                self.next_if(&[")"])?;
+                Some(code)
+            }
+            number if number.chars().all(|c| c.is_ascii_digit()) => Some(Code(format!("{number}i64"), false)),
+            _ => None,
+        }
+    }
+}
+
+/// Turns the C expression of a `Plural-Forms` header, such as `(n > 1)`, to Rust code evaluating to the index of the plural form.
+/// The count is read from `count_code`, which must evaluate to an `i64`.
+pub(crate) fn plural_formula_to_code(formula: &str, count_code: &str) -> Option<String> {
+    let formula = formula.trim().trim_end_matches(';');
+    let mut tokens = Vec::new();
+    let mut rest = formula.trim_start();
+    while !rest.is_empty() {
+        let len = match rest.chars().next()? {
+            c if c.is_ascii_digit() => rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len()),
+            _ if ["||", "&&", "==", "!=", "<=", ">="].iter().any(|op| rest.starts_with(op)) => 2,
+            'n' | '?' | ':' | '(' | ')' | '!' | '<' | '>' | '+' | '-' | '*' | '/' | '%' => 1,
+            _ => return None,
+        };
+        tokens.push(&rest[..len]);
+        rest = rest[len..].trim_start();
+    }
+
+    let mut parser = FormulaParser { tokens, position: 0, count_code };
+    let code = parser.ternary()?;
+    if parser.peek().is_some() {
+        return None;
+    }
+    Some(code.int())
+}
+
+#[test]
+fn test_plural_formula_to_code() {
+    assert_eq!(plural_formula_to_code("(n > 1)", "n").as_deref(), Some("((n > 1i64) as i64)"));
+    assert_eq!(plural_formula_to_code("0;", "n").as_deref(), Some("0i64"));
+    assert_eq!(plural_formula_to_code("n != 1", "count").as_deref(), Some("((count != 1i64) as i64)"));
+    assert_eq!(
+        plural_formula_to_code("n%10==1 && n%100!=11 ? 0 : n != 0 ? 1 : 2", "n").as_deref(),
+        Some("(if (((n % 10i64) == 1i64) && ((n % 100i64) != 11i64)) { 0i64 } else { (if (n != 0i64) { 1i64 } else { 2i64 }) })")
+    );
+    assert_eq!(plural_formula_to_code("n > ", "n"), None);
+    assert_eq!(plural_formula_to_code("x == 1", "n"), None);
+}
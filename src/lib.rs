@@ -5,16 +5,20 @@ use proc_macro::TokenStream;
 
 mod args;
 mod codegen;
+mod events;
 mod sink;
 mod html_element;
 #[cfg(feature = "i18n")]
 mod i18n;
+#[cfg(feature = "raw-html")]
+mod raw_html;
 mod text_part;
 mod config;
 mod helper;
 pub(crate) use {
     crate::args::*,
     crate::codegen::*,
+    crate::events::*,
     crate::sink::*,
     crate::html_element::*,
     crate::config::*,
@@ -26,6 +30,8 @@ pub(crate) use {
 };
 #[cfg(feature = "i18n")]
 pub(crate) use crate::i18n::*;
+#[cfg(feature = "raw-html")]
+pub(crate) use crate::raw_html::*;
 
 /// Reads a file and replaces the variables it contains with the supplied values. Produces a Yew html! macro invocation.
 /// 
@@ -0,0 +1,36 @@
+use crate::*;
+
+/// Source of the sanitizer embedded in generated code
+const SANITIZER_CODE: &str = include_str!("raw_html/sanitizer.rs");
+
+/// Turns the `html` attribute of an element into Rust code for Yew, producing the children of the element
+pub(crate) fn raw_html_to_code(value: &str, sanitize: bool, depth: usize, opts: &mut Vec<String>, iters: &mut Vec<String>, args: &Args) -> String {
+    let tabs = "    ".repeat(depth);
+    let [TextPart::Expression(_)] = TextPart::parse(value, args).as_slice() else {
+        abort!(args.path_span, "The html attribute must be a variable");
+    };
+    let content = TextPart::parse(value, args)[0].to_code(opts, iters, args);
+    match sanitize {
+        true => format!("\n{tabs}{{{{ {SANITIZER_CODE} yew::Html::from_html_unchecked(yew::AttrValue::from(sanitize_html(&({content}).to_string()))) }}}}"),
+        false => format!("\n{tabs}{{yew::Html::from_html_unchecked(yew::AttrValue::from(({content}).to_string()))}}"),
+    }
+}
+
+#[cfg(test)]
+mod sanitizer {
+    include!("raw_html/sanitizer.rs");
+
+    #[test]
+    fn test_sanitize_html() {
+        assert_eq!(sanitize_html("<p>Hello <b>World</b></p>"), "<p>Hello <b>World</b></p>");
+        assert_eq!(sanitize_html("<p onclick=\"alert(1)\" class=note>Hi</p>"), "<p class=\"note\">Hi</p>");
+        assert_eq!(sanitize_html("<script>alert(1)</script>Text"), "Text");
+        assert_eq!(sanitize_html("<blink>Text</blink> 1 < 2"), "Text 1 &lt; 2");
+        assert_eq!(sanitize_html("<!-- comment --><br/>"), "<br>");
+        assert_eq!(sanitize_html("<a href=\"https://example.com\">ok</a>"), "<a href=\"https://example.com\">ok</a>");
+        assert_eq!(sanitize_html("<a href=\"/relative?a=1&b=2\">ok</a>"), "<a href=\"/relative?a=1&b=2\">ok</a>");
+        assert_eq!(sanitize_html("<a href=\" java\tscript:alert(1)\">no</a>"), "<a>no</a>");
+        assert_eq!(sanitize_html("<a href='javascript&colon;alert(1)'>no</a>"), "<a>no</a>");
+        assert_eq!(sanitize_html("<img src=x title='\"><script>'>"), "<img src=\"x\" title=\"&quot;&gt;&lt;script&gt;\">");
+    }
+}
@@ -0,0 +1,129 @@
+/// Sanitizes HTML using an allowlist of elements and attributes.
+/// Disallowed elements are removed but their text is kept, except for elements whose content is never meant to be displayed.
+#[allow(dead_code)]
+fn sanitize_html(html: &str) -> String {
+    const ALLOWED_ELEMENTS: &[&str] = &[
+        "a", "abbr", "b", "blockquote", "br", "code", "dd", "del", "div", "dl", "dt", "em", "figcaption", "figure",
+        "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins", "kbd", "li", "mark", "ol", "p", "pre", "q",
+        "s", "small", "span", "strong", "sub", "sup", "table", "tbody", "td", "tfoot", "th", "thead", "tr", "u", "ul",
+    ];
+    const DROPPED_ELEMENTS: &[&str] = &["script", "style", "template", "iframe", "object", "noscript", "textarea", "title"];
+    const ALLOWED_ATTRIBUTES: &[&str] = &["href", "src", "alt", "title", "class", "colspan", "rowspan"];
+    const URL_ATTRIBUTES: &[&str] = &["href", "src"];
+    const SAFE_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+    fn is_safe_url(url: &str) -> bool {
+        let url = url.chars().filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control()).collect::<String>();
+        match url.find([':', '/', '?', '#']) {
+            Some(idx) if url[idx..].starts_with(':') => SAFE_SCHEMES.contains(&url[..idx].to_ascii_lowercase().as_str()),
+            Some(idx) => !url[..idx].contains('&'),
+            None => !url.contains('&'),
+        }
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+    }
+
+    let mut output = String::with_capacity(html.len());
+    let mut dropped_until: Option<String> = None;
+    let mut rest = html;
+    while let Some(idx) = rest.find('<') {
+        if dropped_until.is_none() {
+            output.push_str(&escape(&rest[..idx]));
+        }
+        rest = &rest[idx..];
+
+        // Skip comments
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or_default();
+            continue;
+        }
+
+        // Parse the tag
+        let closing = rest.starts_with("</");
+        let name_start = if closing { 2 } else { 1 };
+        let name_len = rest[name_start..].find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len() - name_start);
+        let mut quote = None;
+        let tag_end = rest.char_indices().find(|&(_, c)| match quote {
+            Some(q) if c == q => { quote = None; false },
+            Some(_) => false,
+            None if c == '"' || c == '\'' => { quote = Some(c); false },
+            None => c == '>',
+        });
+        let Some((tag_len, _)) = tag_end.filter(|_| name_len > 0) else {
+            if dropped_until.is_none() {
+                output.push_str("&lt;");
+            }
+            rest = &rest[1..];
+            continue;
+        };
+        let name = rest[name_start..name_start + name_len].to_ascii_lowercase();
+        let attrs = &rest[name_start + name_len..tag_len];
+        rest = &rest[tag_len + 1..];
+
+        // Handle elements whose content is dropped
+        if let Some(dropped) = &dropped_until {
+            if closing && &name == dropped {
+                dropped_until = None;
+            }
+            continue;
+        }
+        if DROPPED_ELEMENTS.contains(&name.as_str()) {
+            if !closing && !attrs.trim_end().ends_with('/') {
+                dropped_until = Some(name);
+            }
+            continue;
+        }
+        if !ALLOWED_ELEMENTS.contains(&name.as_str()) {
+            continue;
+        }
+        if closing {
+            output.push_str(&format!("</{name}>"));
+            continue;
+        }
+
+        // Keep allowed attributes
+        output.push('<');
+        output.push_str(&name);
+        let mut attrs = attrs.trim_end_matches('/');
+        loop {
+            attrs = attrs.trim_start();
+            let attr_name_len = attrs.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(attrs.len());
+            if attr_name_len == 0 {
+                break;
+            }
+            let attr_name = attrs[..attr_name_len].to_ascii_lowercase();
+            attrs = attrs[attr_name_len..].trim_start();
+            let mut value = None;
+            if let Some(after_equal) = attrs.strip_prefix('=') {
+                let after_equal = after_equal.trim_start();
+                let (raw_value, after_value) = match after_equal.chars().next() {
+                    Some(quote @ ('"' | '\'')) => match after_equal[1..].find(quote) {
+                        Some(end) => (&after_equal[1..end + 1], &after_equal[end + 2..]),
+                        None => (&after_equal[1..], ""),
+                    },
+                    _ => {
+                        let end = after_equal.find(char::is_whitespace).unwrap_or(after_equal.len());
+                        (&after_equal[..end], &after_equal[end..])
+                    }
+                };
+                value = Some(raw_value);
+                attrs = after_value;
+            }
+            if !ALLOWED_ATTRIBUTES.contains(&attr_name.as_str()) {
+                continue;
+            }
+            let value = value.unwrap_or_default();
+            if URL_ATTRIBUTES.contains(&attr_name.as_str()) && !is_safe_url(value) {
+                continue;
+            }
+            output.push_str(&format!(" {attr_name}=\"{}\"", escape(value)));
+        }
+        output.push('>');
+    }
+    if dropped_until.is_none() {
+        output.push_str(&escape(rest));
+    }
+    output
+}
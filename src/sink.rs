@@ -1,4 +1,4 @@
-use html5ever::{tokenizer::{TokenSink, Token as HtmlToken, TokenSinkResult, TagKind, Tokenizer, TokenizerOpts, BufferQueue}};
+use html5ever::{tokenizer::{TokenSink, Token as HtmlToken, TokenSinkResult, TagKind, Tokenizer, TokenizerOpts, BufferQueue, states::RawKind}};
 use crate::*;
 
 pub(crate) struct HtmlSink<'a> {
@@ -33,7 +33,19 @@ impl<'a> TokenSink for HtmlSink<'a> {
                             Some(container) => container.children.push(HtmlPartWithLine { part: HtmlPart::Element(element), line: line_number as usize }),
                             None => self.html_parts.push(HtmlPartWithLine { part: HtmlPart::Element(element), line: line_number as usize }),
                         },
-                        false => self.opened_elements.push(element)
+                        false => {
+                            // Raw-text elements contain text that must not be parsed as markup
+                            let raw_kind = match element.name.as_str() {
+                                "style" => Some(RawKind::Rawtext),
+                                "script" => Some(RawKind::ScriptData),
+                                "textarea" => Some(RawKind::Rcdata),
+                                _ => None,
+                            };
+                            self.opened_elements.push(element);
+                            if let Some(raw_kind) = raw_kind {
+                                return TokenSinkResult::RawData(raw_kind);
+                            }
+                        }
                     }
                 },
                 TagKind::EndTag => {
@@ -60,9 +72,12 @@ impl<'a> TokenSink for HtmlSink<'a> {
 }
 
 pub(crate) fn read_template(args: &Args) -> Element {
-    let template = match std::fs::read_to_string(&args.path) {
-        Ok(template) => template,
-        Err(e) => abort!(args.path_span, "Failed to read template file at {}: {}", args.path, e),
+    let template = match &args.inline {
+        Some(template) => template.to_owned(),
+        None => match std::fs::read_to_string(crate_path(&args.path)) {
+            Ok(template) => template,
+            Err(e) => abort!(args.path_span, "Failed to read template file at {}: {}", args.path, e),
+        },
     };
     let mut html_parts = Vec::new();
     let html_sink = HtmlSink { html_parts: &mut html_parts, opened_elements: Vec::new(), args };
@@ -71,6 +86,29 @@ pub(crate) fn read_template(args: &Args) -> Element {
     buffer_queue.push_back(template.into());
     let _  = html_tokenizer.feed(&mut buffer_queue);
     html_tokenizer.end();
+
+    // Select the requested template section
+    let section_name = |part: &HtmlPartWithLine| match &part.part {
+        HtmlPart::Element(el) if el.name == "template" => el.open_attrs.iter().find(|(n, _)| n == "name").map(|(_, v)| v.to_owned()),
+        _ => None,
+    };
+    let html_parts = match &args.section {
+        Some(section) => {
+            let Some(idx) = html_parts.iter().position(|p| section_name(p).as_ref() == Some(section)) else {
+                abort!(args.path_span, "Template section {:?} not found in {}", section, args.path);
+            };
+            let HtmlPart::Element(section) = html_parts.swap_remove(idx).part else { unreachable!() };
+            section.children
+        }
+        None => {
+            let sections = html_parts.iter().filter_map(section_name).collect::<Vec<_>>();
+            if !sections.is_empty() {
+                abort!(args.path_span, "{} contains several templates, please select one of them by appending #name to the path (available: {})", args.path, sections.join(", "));
+            }
+            html_parts
+        }
+    };
+
     let mut root = Element {
         name: "".to_string(),
         open_attrs: Vec::new(),
@@ -78,6 +116,6 @@ pub(crate) fn read_template(args: &Args) -> Element {
         self_closing: false,
         children: html_parts,
     };
-    root.clean_text();
+    root.clean_text(args.config.whitespace, args);
     root
 }
@@ -10,27 +10,116 @@ pub(crate) enum TextPart {
 }
 
 impl TextPart {
+    /// Returns the markers opening and closing verbatim blocks
+    fn raw_block_bounds(args: &Args) -> (String, String) {
+        let (open, close) = &args.config.variable_bounds;
+        (format!("{open}{open}raw{close}{close}"), format!("{open}{open}/raw{close}{close}"))
+    }
+
+    /// Returns the length of the template comment at the beginning of `s`, if there is one
+    fn comment_len(s: &str, args: &Args) -> Option<usize> {
+        let (open, close) = &args.config.variable_bounds;
+        let after_open = s.strip_prefix(open.as_str())?;
+        let end_marker = match after_open.starts_with("!--") {
+            true => format!("--{close}"),
+            false if after_open.starts_with('!') => close.to_owned(),
+            false => return None,
+        };
+        match after_open.find(&end_marker) {
+            Some(idx) => Some(open.len() + idx + end_marker.len()),
+            None => abort!(args.path_span, "Missing end of template comment in {}", args.path),
+        }
+    }
+
+    /// Removes template comments from HTML text, leaving verbatim blocks and escaped delimiters untouched
+    pub(crate) fn strip_comments(mut s: &str, args: &Args) -> String {
+        let open = &args.config.variable_bounds.0;
+        let (raw_open, raw_close) = Self::raw_block_bounds(args);
+        let mut result = String::new();
+        while let Some(idx) = s.find(open.as_str()) {
+            result.push_str(&s[..idx]);
+            s = &s[idx..];
+            if s.starts_with(&raw_open) {
+                let len = s.find(&raw_close).map(|idx| idx + raw_close.len()).unwrap_or(s.len());
+                result.push_str(&s[..len]);
+                s = &s[len..];
+            } else if let Some(len) = Self::comment_len(s, args).filter(|_| !result.ends_with('\\')) {
+                s = &s[len..];
+            } else {
+                result.push_str(open);
+                s = &s[open.len()..];
+            }
+        }
+        result.push_str(s);
+        result
+    }
+
     /// Parses HTML text into a list of text parts
     pub(crate) fn parse(mut s: &str, args: &Args) -> Vec<TextPart> {
+        let (raw_open, raw_close) = Self::raw_block_bounds(args);
         let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut strip_next = false;
 
-        while let Some(text) = get_all_before_strict(s, &args.config.variable_bounds.0) {
-            s = &s[text.len() + args.config.variable_bounds.0.len()..];
-            if !text.is_empty() {
-                parts.push(TextPart::Literal(text.to_string()));
+        while let Some(idx) = s.find(args.config.variable_bounds.0.as_str()) {
+            let text = match strip_next {
+                true => s[..idx].trim_start(),
+                false => &s[..idx],
+            };
+            literal.push_str(text);
+            s = &s[idx..];
+            strip_next = false;
+
+            // Handle verbatim blocks
+            if let Some(after_raw_open) = s.strip_prefix(&raw_open) {
+                let Some(content) = get_all_before_strict(after_raw_open, &raw_close) else {
+                    abort!(args.path_span, "Missing end of verbatim block in {}", args.path);
+                };
+                literal.push_str(content);
+                s = &after_raw_open[content.len() + raw_close.len()..];
+                continue;
             }
-            if s.is_empty() {
-                break;
+
+            // Handle escaped delimiters
+            if literal.ends_with('\\') {
+                literal.pop();
+                literal.push_str(&args.config.variable_bounds.0);
+                s = &s[args.config.variable_bounds.0.len()..];
+                continue;
             }
+
+            // Handle template comments
+            if let Some(len) = Self::comment_len(s, args) {
+                s = &s[len..];
+                continue;
+            }
+
+            s = &s[args.config.variable_bounds.0.len()..];
             let var = match get_all_before_strict(s, &args.config.variable_bounds.1) {
                 Some(var) => var,
                 None => abort!(args.path_span, "Missing closing variable separator in html text"),
             };
             s = &s[var.len() + args.config.variable_bounds.1.len()..];
+
+            // Handle whitespace control markers
+            if var.starts_with('~') {
+                literal.truncate(literal.trim_end().len());
+            }
+            strip_next = var.ends_with('~');
+            let var = var.trim_matches('~').trim();
+
+            if !literal.is_empty() {
+                parts.push(TextPart::Literal(std::mem::take(&mut literal)));
+            }
             parts.push(TextPart::Expression(var.to_string()));
         }
-        if !s.is_empty() {
-            parts.push(TextPart::Literal(s.to_string()));
+        let s = match strip_next {
+            true => s.trim_start(),
+            false => s,
+        };
+        literal.push_str(s);
+        if !literal.is_empty() {
+            parts.push(TextPart::Literal(literal));
         }
 
         parts
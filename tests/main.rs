@@ -1,5 +1,13 @@
+#![allow(unused_variables, dead_code)]
+
+use yew::prelude::*;
 use yew_template::*;
 
+struct Folder {
+    name: &'static str,
+    children: Vec<Folder>,
+}
+
 struct Person {
     first_name: String,
     last_name: String,
@@ -12,5 +20,128 @@ fn main() {
     let color = "red";
     let locale = String::from("en");
 
+    let name = "World";
+    let _raw = template_html!("tests/raw.html", name);
+    let _whitespace = template_html!("tests/whitespace.html", name, separator="|");
+    let _syntax = template_html!("tests/syntax.html", name);
+    let _with = template_html!("tests/with.html", price=10, currency="eur", opt_discount={Some(5)}, url="https://example.com");
+    let root = Folder { name: "root", children: vec![Folder { name: "src", children: Vec::new() }] };
+    let _recursive = template_html!("tests/recursive.html", folder={&root});
+    let _dynamic = template_html!("tests/dynamic.html", heading="h2", title="Title", list={String::from("ol")}, level=3);
+    let messages = ["Hi"];
+    let _plurals = template_html!("tests/plurals.html", count=3, unread={messages.len()});
+    let _contexts = template_html!("tests/contexts.html");
+    let _notranslate = template_html!("tests/notranslate.html");
+    let _attributes = template_html!("tests/attributes.html", name);
+    let _sentences = template_html!("tests/sentences.html", name);
+    let _badge = template_html!("tests/bits.html#badge", label="New");
+    let _empty = template_html!("tests/bits.html#empty");
+    let _relative = template_html!("bits.html#badge", template_rel = "file", label="Old");
+    let _inline = template_html!(inline = r#"<p class="greeting">Hello {{name}}</p>"#, name);
+    let _urls = template_html!("tests/urls.html", link="javascript:alert(1)", user_id=42, target="/submit");
+    #[cfg(feature = "raw-html")]
+    let _raw_html = template_html!("tests/raw_html.html", comment="<p>Hello <b>World</b></p>", trusted={String::from("<em>Hi</em>")});
     let _html = template_html!("tests/test.handlebars", value="tes", value2={5.to_string()}, boobool, opt_value={Some("tes")}, opt_value2={Some("optvalue2")}, names_iter={["Edouart", "Foobar"].iter()}, background_color="#aaa", person, has_password = true, ...);
 }
+
+enum Msg {
+    Submit,
+    SetName(String),
+    Increment(usize),
+    Reset,
+    SetAgree(bool),
+}
+
+struct Form {
+    name: String,
+    count: usize,
+    agree: bool,
+    input_ref: NodeRef,
+    canvas_ref: NodeRef,
+}
+
+impl Component for Form {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Form { name: String::new(), count: 0, agree: false, input_ref: NodeRef::default(), canvas_ref: NodeRef::default() }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::Submit => return false,
+            Msg::SetName(name) => self.name = name,
+            Msg::Increment(step) => self.count += step,
+            Msg::Reset => self.count = 0,
+            Msg::SetAgree(agree) => self.agree = agree,
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let locale = String::from("en");
+        let set_agree = ctx.link().callback(Msg::SetAgree);
+        yew::html! {
+            <>
+                {template_html!("tests/events.html", name={self.name.clone()}, step=2, count={self.count}, ...)}
+                {template_html!("tests/bind.html", name={self.name.clone()}, agree={self.agree}, set_agree)}
+                {template_html!("tests/refs.html", input_ref={&self.input_ref}, canvas_ref={&self.canvas_ref})}
+            </>
+        }
+    }
+}
+
+/// Returns the values of an attribute in a node and its descendants, in document order
+fn attribute_values(node: &Html, name: &str) -> Vec<String> {
+    match node {
+        Html::VTag(tag) => tag.attributes.iter()
+            .filter(|(n, _)| *n == name)
+            .map(|(_, v)| v.to_string())
+            .chain(tag.children().iter().flat_map(|child| attribute_values(child, name)))
+            .collect(),
+        Html::VList(list) => list.iter().flat_map(|child| attribute_values(child, name)).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[test]
+fn safe_urls() {
+    let locale = String::from("en");
+    let html = template_html!("tests/urls.html", link="javascript:alert(1)", user_id=42, target="javascript:void(0)");
+    assert_eq!(attribute_values(&html, "href"), ["about:invalid", "https://example.com"]);
+    assert_eq!(attribute_values(&html, "src"), ["/avatars/42.png"]);
+    assert_eq!(attribute_values(&html, "action"), ["javascript:void(0)"]);
+
+    for (link, expected) in [(" JaVaScRiPt:alert(1)", "about:invalid"), ("java\tscript:alert(1)", "about:invalid"), ("data:text/html,hi", "about:invalid"), ("mailto:a@example.com", "mailto:a@example.com"), ("/profile?tab=a:b", "/profile?tab=a:b"), ("profile", "profile")] {
+        let html = template_html!("tests/urls.html", link, user_id=42, target="/submit");
+        assert_eq!(attribute_values(&html, "href")[0], expected, "for {link:?}");
+    }
+}
+
+#[test]
+fn rel_variable() {
+    let locale = String::from("en");
+    let html = template_html!("tests/bits.html#link", rel="noopener");
+    assert_eq!(attribute_values(&html, "rel"), ["noopener"]);
+}
+
+#[test]
+fn sentence_placeholder_attributes() {
+    let name = "Ana";
+    for (locale, title) in [("fr", "Page suivante"), ("en", "Next page")] {
+        let locale = String::from(locale);
+        let html = template_html!("tests/sentences.html", name);
+        assert_eq!(attribute_values(&html, "title"), [title], "for {locale}");
+    }
+}
+
+#[test]
+fn locale_fallback() {
+    let name = "Ana";
+    for (locale, title) in [("fr", "Page suivante"), ("FR", "Page suivante"), ("fr-CA", "Page suivante"), ("fr_FR", "Page suivante"), ("de-DE", "Next page"), ("", "Next page")] {
+        let locale = String::from(locale);
+        let html = template_html!("tests/sentences.html", name);
+        assert_eq!(attribute_values(&html, "title"), [title], "for {locale:?}");
+    }
+}